@@ -0,0 +1,68 @@
+//! Derive macros for [dusty-room]. Use them through their re-exports in `dusty_room::wad`.
+//!
+//! [dusty-room]: https://github.com/jkugelman/dusty-room/
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+
+/// Derives `dusty_room::wad::Record` for a struct whose fields are all records. Fields are laid out
+/// in declaration order with no padding, and the struct's size is the sum of its fields' sizes.
+#[proc_macro_derive(Record)]
+pub fn derive_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match record(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn record(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(input, "Record can only be derived for structs")),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let record = quote!(::dusty_room::wad::Record);
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    // Struct expressions evaluate their fields in the order they're written, so fields are
+    // decoded in declaration order.
+    let (decode, encode) = match fields {
+        Fields::Named(_) => {
+            let names: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+            (
+                quote!(Self { #(#names: <#types as #record>::decode(buf),)* }),
+                quote!(#(#record::encode(&self.#names, buf);)*),
+            )
+        }
+        Fields::Unnamed(_) => {
+            let indexes: Vec<_> = (0..fields.len()).map(Index::from).collect();
+            (
+                quote!(Self(#(<#types as #record>::decode(buf),)*)),
+                quote!(#(#record::encode(&self.#indexes, buf);)*),
+            )
+        }
+        Fields::Unit => (quote!(Self), quote!()),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #record for #name #ty_generics #where_clause {
+            const SIZE: usize = 0 #(+ <#types as #record>::SIZE)*;
+
+            #[allow(unused_variables)]
+            fn decode(buf: &mut &[u8]) -> Self {
+                #decode
+            }
+
+            #[allow(unused_variables)]
+            fn encode(&self, buf: &mut ::std::vec::Vec<u8>) {
+                #encode
+            }
+        }
+    })
+}
@@ -1,7 +1,9 @@
+use crate::assets::ColormapBank;
 use crate::assets::FlatBank;
 use crate::assets::PaletteBank;
+use crate::assets::PatchBank;
 use crate::assets::TextureBank;
-use crate::wad::{self, Wad};
+use crate::wad::{self, Diagnostics, Wad};
 
 /// Holds all of the fixed assets loaded from a [`Wad`]: graphics, sounds, music, text strings, etc.
 /// Map data is stored [elsewhere] since typically only one map is loaded at a time.
@@ -10,18 +12,33 @@ use crate::wad::{self, Wad};
 #[derive(Debug)]
 pub struct Assets {
     pub palette_bank: PaletteBank,
+    pub colormap_bank: ColormapBank,
     pub flat_bank: FlatBank,
+    pub patch_bank: PatchBank,
     pub texture_bank: TextureBank,
 }
 
 impl Assets {
     /// Loads assets from a [`Wad`].
     pub fn load(wad: &Wad) -> wad::Result<Self> {
-        let palette_bank = PaletteBank::load(wad)?;
-        let flat_bank = FlatBank::load(wad)?;
-        let texture_bank = TextureBank::load(wad)?;
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
+
+    /// Loads assets from a [`Wad`], recovering from malformed data if `diagnostics` is [lenient].
+    ///
+    /// Textures are [checked] against the patch bank, so a texture that uses a missing patch is an
+    /// error unless `diagnostics` is lenient.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [checked]: TextureBank::load_resolved
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let palette_bank = PaletteBank::load_with(wad, diagnostics)?;
+        let colormap_bank = ColormapBank::load_with(wad, diagnostics)?;
+        let flat_bank = FlatBank::load_with(wad, diagnostics)?;
+        let patch_bank = PatchBank::load_with(wad, diagnostics)?;
+        let texture_bank = TextureBank::load_resolved(wad, &patch_bank, diagnostics)?;
 
-        Ok(Assets { palette_bank, flat_bank, texture_bank })
+        Ok(Assets { palette_bank, colormap_bank, flat_bank, patch_bank, texture_bank })
     }
 }
 
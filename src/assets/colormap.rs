@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Deref, Index};
+
+use crate::wad::{self, Diagnostics, Lump, LumpName, MalformedKind, Namespace, ToLumpName, Wad};
+
+/// The colormaps from the `COLORMAP` lump, plus any extra sets of colormaps that Boom maps can use,
+/// found between the `C_START` and `C_END` marker lumps.
+///
+/// The bank dereferences to the standard [`ColormapSet`] from `COLORMAP`, which is what vanilla
+/// DOOM uses to shade everything.
+///
+/// # Examples
+///
+/// Shade a wall in a dim sector:
+///
+/// ```no_run
+/// use dusty_room::assets::ColormapBank;
+/// use dusty_room::wad::Wad;
+///
+/// let wad = Wad::load("doom.wad")?;
+/// let colormaps = ColormapBank::load(&wad)?;
+///
+/// // The scale is 16.16 fixed point, as in vanilla DOOM's renderer.
+/// let colormap = colormaps.scalelight(96, 0, 1 << 16);
+/// let shaded = colormap[176];
+/// #
+/// # Ok::<(), dusty_room::wad::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ColormapBank {
+    standard: ColormapSet,
+    extra: BTreeMap<LumpName, ColormapSet>,
+}
+
+impl ColormapBank {
+    /// Loads the `COLORMAP` lump, along with Boom's extra colormaps between the `C_START` and
+    /// `C_END` marker lumps.
+    ///
+    /// Extra colormaps are [merged] across all of the WAD's files.
+    ///
+    /// [merged]: Wad::namespace
+    pub fn load(wad: &Wad) -> wad::Result<Self> {
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
+
+    /// Loads the `COLORMAP` lump and Boom's extra colormaps. If `diagnostics` is [lenient], a
+    /// partial colormap at the end of a lump is ignored, missing light levels are filled in with
+    /// copies of the last one, and when a file has duplicate extra colormaps that the WAD's
+    /// [`DuplicatePolicy`] doesn't allow the last one is used.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [`DuplicatePolicy`]: crate::wad::DuplicatePolicy
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let standard =
+            ColormapSet::load_with(&wad.lump_with("COLORMAP", diagnostics)?, diagnostics)?;
+        let mut extra = BTreeMap::new();
+
+        for lump in wad.namespace_with(Namespace::Colormaps, diagnostics)? {
+            if lump.is_empty() {
+                continue;
+            }
+
+            extra.insert(lump.name(), ColormapSet::load_with(&lump, diagnostics)?);
+        }
+
+        Ok(Self { standard, extra })
+    }
+
+    /// The standard colormaps from `COLORMAP`.
+    pub fn standard(&self) -> &ColormapSet {
+        &self.standard
+    }
+
+    /// Looks up a set of colormaps by lump name. `COLORMAP` is the standard set; other names are
+    /// Boom's extra colormaps, which sectors can use through linedef type 242.
+    pub fn named(&self, name: impl ToLumpName) -> Option<&ColormapSet> {
+        let name = name.to_lump_name()?;
+
+        if name == "COLORMAP" {
+            Some(&self.standard)
+        } else {
+            self.extra.get(&name)
+        }
+    }
+
+    /// Boom's extra colormaps, indexed by name.
+    pub fn extra(&self) -> &BTreeMap<LumpName, ColormapSet> {
+        &self.extra
+    }
+}
+
+impl Deref for ColormapBank {
+    type Target = ColormapSet;
+
+    fn deref(&self) -> &Self::Target {
+        &self.standard
+    }
+}
+
+/// A lump full of colormaps in the same layout as `COLORMAP`: 32 light levels from brightest to
+/// darkest, then the invulnerability colormap, then an all-black one. Part of a [`ColormapBank`].
+///
+/// The light level lookups mirror vanilla DOOM's `zlight` and `scalelight` tables for a full
+/// screen at high detail, so sectors fade into the distance just as they do in the original.
+#[derive(Debug, Clone)]
+pub struct ColormapSet {
+    colormaps: Vec<Colormap>,
+}
+
+/// Number of colormaps in `COLORMAP` used for lighting. The rest are special effects.
+const NUM_COLORMAPS: usize = 32;
+const COLORMAP_BYTES: usize = 256;
+
+/// Sector light levels are reduced to this many steps by shifting them right `LIGHT_SEG_SHIFT`
+/// bits.
+const LIGHT_LEVELS: i32 = 16;
+const LIGHT_SEG_SHIFT: u32 = 4;
+
+/// Walls and sprites are lit by their scale, shifted right `LIGHT_SCALE_SHIFT` bits.
+const MAX_LIGHT_SCALE: i32 = 48;
+const LIGHT_SCALE_SHIFT: u32 = 12;
+
+/// Floors and ceilings are lit by their distance, shifted right `LIGHT_Z_SHIFT` bits.
+const MAX_LIGHT_Z: i32 = 128;
+const LIGHT_Z_SHIFT: u32 = 20;
+
+const DIST_MAP: i32 = 2;
+const SCREEN_WIDTH: i32 = 320;
+
+impl ColormapSet {
+    /// Loads a set of colormaps from a lump. There must be at least 32, one for each light level.
+    pub fn load(lump: &Lump) -> wad::Result<Self> {
+        Self::load_with(lump, &mut Diagnostics::strict())
+    }
+
+    /// Loads a set of colormaps from a lump. If `diagnostics` is [lenient], a partial colormap at
+    /// the end of the lump is ignored, and missing light levels are filled in with copies of the
+    /// last one.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(lump: &Lump, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let mut cursor = lump.cursor();
+        let count = lump.size() / COLORMAP_BYTES;
+        let tables = cursor.read_array::<[u8; COLORMAP_BYTES]>(count)?;
+        let mut colormaps: Vec<Colormap> = tables.into_iter().map(Colormap::new).collect();
+        if let Err(err) = cursor.done() {
+            diagnostics.report(err)?;
+        }
+
+        if colormaps.len() < NUM_COLORMAPS {
+            diagnostics.report(lump.error(
+                MalformedKind::Truncated,
+                format!("need {} colormaps, only {} found", NUM_COLORMAPS, colormaps.len()),
+            ))?;
+
+            let last = colormaps.last().cloned().unwrap_or_else(Colormap::identity);
+            colormaps.resize(NUM_COLORMAPS, last);
+        }
+
+        Ok(Self { colormaps })
+    }
+
+    /// The number of colormaps in the set, including special effects.
+    pub fn count(&self) -> usize {
+        self.colormaps.len()
+    }
+
+    /// Looks up a colormap number. 0 is the brightest light level and 31 the darkest.
+    pub fn get(&self, index: usize) -> Option<&Colormap> {
+        self.colormaps.get(index)
+    }
+
+    /// The grayscale colormap shown while the player is invulnerable, if there is one.
+    pub fn invulnerability(&self) -> Option<&Colormap> {
+        self.get(NUM_COLORMAPS)
+    }
+
+    /// The colormap for a floor or ceiling in a sector with `light_level`, `distance` away from
+    /// the viewer. Like vanilla's `zlight` table, `distance` is in 16.16 fixed point map units.
+    ///
+    /// `extra_light` brightens everything by that many of the 16 light steps, the way a muzzle
+    /// flash does. It's 0 normally.
+    pub fn zlight(&self, light_level: u8, extra_light: u8, distance: i32) -> &Colormap {
+        &self.colormaps[zlight_index(light_level, extra_light, distance)]
+    }
+
+    /// The colormap for a wall or sprite in a sector with `light_level`, drawn at `scale`. Like
+    /// vanilla's `scalelight` table, `scale` is in 16.16 fixed point, where 1.0 means one map unit
+    /// spans one pixel on a 320-pixel-wide screen.
+    ///
+    /// `extra_light` brightens everything by that many of the 16 light steps, as with [`zlight`].
+    ///
+    /// [`zlight`]: Self::zlight
+    pub fn scalelight(&self, light_level: u8, extra_light: u8, scale: i32) -> &Colormap {
+        &self.colormaps[scalelight_index(light_level, extra_light, scale)]
+    }
+}
+
+/// The darkest colormap a sector's light level uses, up close. Things fade toward darker colormaps
+/// the farther away they are.
+fn start_map(light_level: u8, extra_light: u8) -> i32 {
+    let level =
+        (i32::from(light_level >> LIGHT_SEG_SHIFT) + i32::from(extra_light)).min(LIGHT_LEVELS - 1);
+    (LIGHT_LEVELS - 1 - level) * 2 * NUM_COLORMAPS as i32 / LIGHT_LEVELS
+}
+
+/// `R_InitLightTables`.
+fn zlight_index(light_level: u8, extra_light: u8, distance: i32) -> usize {
+    let z = (distance >> LIGHT_Z_SHIFT).clamp(0, MAX_LIGHT_Z - 1);
+    // FixedDiv(SCREENWIDTH / 2 * FRACUNIT, (z + 1) << LIGHTZSHIFT)
+    let scale = (i64::from(SCREEN_WIDTH / 2) << 32) / (i64::from(z + 1) << LIGHT_Z_SHIFT);
+    let scale = scale as i32 >> LIGHT_SCALE_SHIFT;
+    let start = start_map(light_level, extra_light);
+    (start - scale / DIST_MAP).clamp(0, NUM_COLORMAPS as i32 - 1) as usize
+}
+
+/// `R_ExecuteSetViewSize`, with a full-width view at high detail.
+fn scalelight_index(light_level: u8, extra_light: u8, scale: i32) -> usize {
+    let scale = (scale >> LIGHT_SCALE_SHIFT).clamp(0, MAX_LIGHT_SCALE - 1);
+    let start = start_map(light_level, extra_light);
+    (start - scale / DIST_MAP).clamp(0, NUM_COLORMAPS as i32 - 1) as usize
+}
+
+/// A table that remaps each palette index to another. `COLORMAP` uses them to darken colors at
+/// lower light levels, and for the invulnerability effect.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Colormap([u8; 256]);
+
+impl Colormap {
+    /// Creates a colormap from a table of 256 palette indices.
+    pub fn new(table: [u8; 256]) -> Self {
+        Self(table)
+    }
+
+    /// A colormap that leaves every color unchanged.
+    pub fn identity() -> Self {
+        Self(std::array::from_fn(|index| index as u8))
+    }
+
+    /// The raw table.
+    pub fn table(&self) -> &[u8; 256] {
+        &self.0
+    }
+}
+
+impl Index<u8> for Colormap {
+    type Output = u8;
+
+    /// Remaps a palette index.
+    fn index(&self, index: u8) -> &Self::Output {
+        &self.0[usize::from(index)]
+    }
+}
+
+impl fmt::Debug for Colormap {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Colormap({:?})", &self.0[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{WadBuilder, WadKind};
+
+    /// Colormap `i` maps every color to `i`.
+    fn colormaps(count: u8) -> Vec<u8> {
+        (0..count).flat_map(|i| [i; COLORMAP_BYTES]).collect()
+    }
+
+    #[test]
+    fn light_tables() {
+        // Full brightness never darkens.
+        assert_eq!(zlight_index(255, 0, i32::MAX), 0);
+        assert_eq!(scalelight_index(255, 0, 0), 0);
+
+        // Pitch black is always the darkest colormap, except right up close.
+        assert_eq!(zlight_index(0, 0, 0), 0);
+        assert_eq!(zlight_index(0, 0, 127 << 20), 31);
+        assert_eq!(zlight_index(0, 0, -1), 0);
+        assert_eq!(scalelight_index(0, 0, 0), 31);
+
+        // Light level 128 starts at colormap 28 and brightens up close.
+        assert_eq!(zlight_index(128, 0, 160 << 16), 21);
+        assert_eq!(zlight_index(143, 0, 160 << 16), 21);
+        assert_eq!(scalelight_index(128, 0, 0), 28);
+        assert_eq!(scalelight_index(128, 0, 1 << 16), 20);
+        assert_eq!(scalelight_index(128, 0, i32::MAX), 5);
+
+        // Each step of extra light is worth 16 light levels, up to full brightness.
+        assert_eq!(scalelight_index(128, 1, 0), scalelight_index(144, 0, 0));
+        assert_eq!(zlight_index(128, 2, 160 << 16), zlight_index(160, 0, 160 << 16));
+        assert_eq!(scalelight_index(240, 1, 0), 0);
+        assert_eq!(scalelight_index(255, 255, 0), 0);
+        assert_eq!(zlight_index(0, 255, 127 << 20), zlight_index(255, 0, 127 << 20));
+    }
+
+    #[test]
+    fn load() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("COLORMAP", colormaps(34));
+        builder.marker("C_START").lump("WATERMAP", colormaps(33)).marker("C_END");
+        let wad = Wad::new(builder.build_file("colormap.wad")?)?;
+        let bank = ColormapBank::load(&wad)?;
+
+        assert_eq!(bank.count(), 34);
+        assert_eq!(bank.get(31).unwrap()[0], 31);
+        assert_eq!(bank.invulnerability().unwrap()[200], 32);
+        assert_eq!(bank.scalelight(128, 0, 1 << 16)[255], 20);
+        assert_eq!(bank.zlight(0, 0, 127 << 20)[255], 31);
+
+        assert_eq!(bank.named("COLORMAP").unwrap().count(), 34);
+        assert_eq!(bank.named("WATERMAP").unwrap().count(), 33);
+        assert_eq!(bank.extra().len(), 1);
+        assert!(bank.named("NOSUCH").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient() -> wad::Result<()> {
+        let file = WadBuilder::new(WadKind::Pwad)
+            .lump("COLORMAP", [colormaps(3), vec![0; 10]].concat())
+            .build_file("short.wad")?;
+        let wad = Wad::new(file)?;
+
+        assert_matches!(ColormapBank::load(&wad), Err(wad::Error::Malformed { .. }));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let bank = ColormapBank::load_with(&wad, &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 2);
+        assert_eq!(diagnostics.warnings()[0].kind(), Some(MalformedKind::TrailingData));
+        assert_eq!(diagnostics.warnings()[1].kind(), Some(MalformedKind::Truncated));
+        assert_eq!(bank.count(), 32);
+        assert_eq!(bank.get(31).unwrap()[0], 2);
+        assert!(bank.invulnerability().is_none());
+
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("COLORMAP", colormaps(34)).marker("C_START");
+        builder.lump("WATERMAP", colormaps(33)).lump("WATERMAP", colormaps(32)).marker("C_END");
+        let wad = Wad::new(builder.build_file("dupes.wad")?)?;
+
+        assert_matches!(ColormapBank::load(&wad), Err(wad::Error::Malformed { .. }));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let bank = ColormapBank::load_with(&wad, &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 1);
+        assert_eq!(diagnostics.warnings()[0].kind(), Some(MalformedKind::Duplicate));
+        assert_eq!(bank.named("WATERMAP").unwrap().count(), 32);
+
+        Ok(())
+    }
+}
@@ -1,21 +1,38 @@
 use std::collections::BTreeMap;
 use std::fmt;
 use std::ops::{Deref, Index};
+use std::sync::OnceLock;
 
 use bytes::Bytes;
 
-use crate::wad::{self, Lump, Wad};
+use crate::assets::IndexedImage;
+use crate::wad::{self, Diagnostics, Lump, LumpName, Namespace, ToLumpName, Wad};
 
 /// A bank of [sector] floor and ceiling textures, indexed by name.
 ///
 /// [sector]: crate::map::Sector
 #[derive(Clone)]
-pub struct FlatBank(BTreeMap<String, Flat>);
+pub struct FlatBank(BTreeMap<LumpName, Flat>);
 
 impl FlatBank {
     /// Loads all the flats from a [`Wad`] found between the `F_START` and `F_END` marker lumps.
+    ///
+    /// Flats are [merged] across all of the WAD's files, so a PWAD can add new flats without hiding
+    /// the IWAD's.
+    ///
+    /// [merged]: Wad::namespace
     pub fn load(wad: &Wad) -> wad::Result<Self> {
-        let lumps = wad.lumps_between("F_START", "F_END")?;
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
+
+    /// Loads all the flats from a [`Wad`]. If `diagnostics` is [lenient], flats that are the wrong
+    /// size are padded or truncated, and when a file has duplicate flats that the WAD's
+    /// [`DuplicatePolicy`] doesn't allow the last one is used. Otherwise both are errors.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [`DuplicatePolicy`]: crate::wad::DuplicatePolicy
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let lumps = wad.namespace_with(Namespace::Flats, diagnostics)?;
         let mut flats = BTreeMap::new();
 
         for lump in lumps {
@@ -23,20 +40,16 @@ impl FlatBank {
                 continue;
             }
 
-            let flat = Flat::load(&lump)?;
-            let existing = flats.insert(flat.name.clone(), flat);
-
-            if existing.is_some() {
-                return Err(lump.error(format!("duplicate flat {}", lump.name())));
-            }
+            let flat = diagnostics.recover(Flat::load(&lump), || Flat::resized(&lump))?;
+            flats.insert(flat.name, flat);
         }
 
         Ok(Self(flats))
     }
 
     /// Looks up a flat name.
-    pub fn get(&self, name: &str) -> Option<&Flat> {
-        self.0.get(name)
+    pub fn get(&self, name: impl ToLumpName) -> Option<&Flat> {
+        self.0.get(&name.to_lump_name()?)
     }
 }
 
@@ -49,8 +62,17 @@ impl Index<&str> for FlatBank {
     }
 }
 
+impl Index<LumpName> for FlatBank {
+    type Output = Flat;
+
+    /// Looks up a flat name.
+    fn index(&self, name: LumpName) -> &Self::Output {
+        self.get(name).expect("flat missing")
+    }
+}
+
 impl Deref for FlatBank {
-    type Target = BTreeMap<String, Flat>;
+    type Target = BTreeMap<LumpName, Flat>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -73,7 +95,7 @@ pub struct Flat {
     /// Name of the flat. Used by [sectors].
     ///
     /// [sectors]: crate::map::Sector
-    pub name: String,
+    pub name: LumpName,
 
     pixels: Bytes,
 }
@@ -85,7 +107,7 @@ impl Flat {
         let height: usize = Self::height().into();
 
         let mut cursor = lump.cursor();
-        let name = lump.name().to_owned();
+        let name = lump.name();
         cursor.need(width * height)?;
         let pixels = cursor.split_to(width * height);
         cursor.done()?;
@@ -93,6 +115,30 @@ impl Flat {
         Ok(Self { name, pixels })
     }
 
+    /// A blank placeholder named `-` and filled with color 0. [Sectors] that were loaded
+    /// [leniently] and name a flat that doesn't exist are drawn with it.
+    ///
+    /// [Sectors]: crate::map::Sector
+    /// [leniently]: crate::map::Sectors::load_with
+    pub fn missing() -> &'static Flat {
+        static MISSING: OnceLock<Flat> = OnceLock::new();
+
+        MISSING.get_or_init(|| Self {
+            name: LumpName::new("-").unwrap(),
+            pixels: Bytes::from_static(&[0; 64 * 64]),
+        })
+    }
+
+    /// Builds a flat from a lump with the wrong amount of data, padding it with color 0 (black in
+    /// the stock palettes) or truncating it as needed.
+    fn resized(lump: &Lump) -> Self {
+        let size = usize::from(Self::width()) * usize::from(Self::height());
+        let mut pixels = lump.data().to_vec();
+        pixels.resize(size, 0);
+
+        Self { name: lump.name(), pixels: pixels.into() }
+    }
+
     /// Width in pixels. Flats are always 64x64.
     pub const fn width() -> u16 {
         64
@@ -102,6 +148,15 @@ impl Flat {
     pub const fn height() -> u16 {
         64
     }
+
+    /// Copies the flat into an [`IndexedImage`]. Flats have no transparent pixels.
+    pub fn to_image(&self) -> IndexedImage {
+        let mut image = IndexedImage::new(Self::width(), Self::height());
+        for (&color, index) in self.pixels.iter().zip(0..) {
+            image.set(index % Self::width(), index / Self::width(), color);
+        }
+        image
+    }
 }
 
 impl fmt::Debug for Flat {
@@ -122,6 +177,7 @@ impl fmt::Display for Flat {
 mod tests {
     use super::*;
     use crate::wad::test::*;
+    use crate::wad::{MalformedKind, WadBuilder, WadKind};
 
     #[test]
     fn load() {
@@ -131,4 +187,22 @@ mod tests {
         assert_matches!(flats.get("GATE2"), Some(_));
         assert_matches!(flats.get("NUKAGE1"), Some(_));
     }
+
+    #[test]
+    fn duplicates() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Iwad);
+        builder.marker("F_START").lump("FLAT1", vec![1; 4096]).lump("FLAT1", vec![2; 4096]);
+        builder.marker("F_END");
+        let wad = Wad::new(builder.build_file("dupes.wad")?)?;
+
+        let err = FlatBank::load(&wad).unwrap_err();
+        assert_eq!(err.kind(), Some(MalformedKind::Duplicate));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let flats = FlatBank::load_with(&wad, &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 1);
+        assert_eq!(flats["FLAT1"].pixels, [2; 4096][..]);
+
+        Ok(())
+    }
 }
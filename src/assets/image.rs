@@ -0,0 +1,171 @@
+use std::fmt;
+
+use crate::assets::{Colormap, Palette};
+
+/// A paletted image, such as a composited [texture] or a [patch]. Each pixel is either a palette
+/// index or transparent.
+///
+/// Pixels are stored in rows, top to bottom. Transparent pixels have index 0 in [`pixels`]; use
+/// [`mask`] or [`get`] to tell them apart from opaque pixels of color 0.
+///
+/// [texture]: crate::assets::Texture
+/// [patch]: crate::assets::Patch
+/// [`pixels`]: Self::pixels
+/// [`mask`]: Self::mask
+/// [`get`]: Self::get
+#[derive(Clone, PartialEq, Eq)]
+pub struct IndexedImage {
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+    mask: Vec<bool>,
+}
+
+impl IndexedImage {
+    /// Creates a fully transparent image.
+    pub fn new(width: u16, height: u16) -> Self {
+        let size = usize::from(width) * usize::from(height);
+        Self { width, height, pixels: vec![0; size], mask: vec![false; size] }
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The palette indices, `height` rows of `width` pixels each. Transparent pixels are 0.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The transparency mask, laid out like [`pixels`]. `true` means opaque.
+    ///
+    /// [`pixels`]: Self::pixels
+    pub fn mask(&self) -> &[bool] {
+        &self.mask
+    }
+
+    /// Returns `true` if every pixel is opaque.
+    pub fn is_opaque(&self) -> bool {
+        self.mask.iter().all(|&opaque| opaque)
+    }
+
+    /// The palette index at (`x`, `y`), or `None` if the pixel is transparent or out of bounds.
+    pub fn get(&self, x: u16, y: u16) -> Option<u8> {
+        let index = self.index(x, y)?;
+        self.mask[index].then(|| self.pixels[index])
+    }
+
+    /// Sets the pixel at (`x`, `y`) to an opaque palette index. Out of bounds pixels are ignored.
+    pub fn set(&mut self, x: u16, y: u16, color: u8) {
+        if let Some(index) = self.index(x, y) {
+            self.pixels[index] = color;
+            self.mask[index] = true;
+        }
+    }
+
+    /// Converts the image to RGBA, 4 bytes per pixel in the same order as [`pixels`]. Opaque
+    /// pixels are looked up in `palette`, after being remapped through `colormap` if one is given.
+    /// Transparent pixels have an alpha of 0.
+    ///
+    /// # Examples
+    ///
+    /// Draw a patch as it looks in a dark room:
+    ///
+    /// ```no_run
+    /// use dusty_room::assets::{Assets, Colormap, Patch};
+    /// use dusty_room::wad::Wad;
+    ///
+    /// let wad = Wad::load("doom.wad")?;
+    /// let assets = Assets::load(&wad)?;
+    /// let palette = assets.palette_bank.get(0).unwrap();
+    ///
+    /// let patch = Patch::load(&wad.lump("TROOA1")?)?;
+    /// let dark = Colormap::identity(); // Or a table from `COLORMAP`.
+    /// let rgba = patch.to_image().to_rgba(palette, Some(&dark));
+    /// assert_eq!(rgba.len(), 4 * usize::from(patch.width) * usize::from(patch.height));
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [`pixels`]: Self::pixels
+    pub fn to_rgba(&self, palette: &Palette, colormap: Option<&Colormap>) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(4 * self.pixels.len());
+
+        for (&color, &opaque) in self.pixels.iter().zip(&self.mask) {
+            if opaque {
+                let color = colormap.map_or(color, |colormap| colormap[color]);
+                rgba.extend(palette.rgba(color));
+            } else {
+                rgba.extend([0; 4]);
+            }
+        }
+
+        rgba
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(usize::from(y) * usize::from(self.width) + usize::from(x))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for IndexedImage {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let Self { width, height, pixels: _, mask } = self;
+
+        fmt.debug_struct("IndexedImage")
+            .field("width", &width)
+            .field("height", &height)
+            .field("opaque", &mask.iter().filter(|&&opaque| opaque).count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::{FlatBank, PaletteBank};
+    use crate::wad::{self, Wad, WadBuilder, WadKind};
+
+    #[test]
+    fn to_rgba() -> wad::Result<()> {
+        // Color `i` is (i, 2i, 3i) in the first palette and gray in the second.
+        let mut playpal: Vec<u8> =
+            (0..=255u8).flat_map(|i| [i, i.wrapping_mul(2), i.wrapping_mul(3)]).collect();
+        playpal.extend([0x80; 768]);
+
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("PLAYPAL", playpal);
+        builder.marker("F_START").lump("FLAT", vec![7; 4096]).marker("F_END");
+        let wad = Wad::new(builder.build_file("rgba.wad")?)?;
+        let palettes = PaletteBank::load(&wad)?;
+        let palette = palettes.get(0).unwrap();
+
+        let mut image = IndexedImage::new(2, 1);
+        image.set(0, 0, 10);
+        assert_eq!(image.to_rgba(palette, None), [10, 20, 30, 255, 0, 0, 0, 0]);
+        assert_eq!(
+            image.to_rgba(palettes.get(1).unwrap(), None),
+            [0x80, 0x80, 0x80, 255, 0, 0, 0, 0]
+        );
+
+        let brighter = Colormap::new(std::array::from_fn(|i| (i as u8).saturating_add(1)));
+        assert_eq!(image.to_rgba(palette, Some(&brighter))[..4], [11, 22, 33, 255]);
+        assert_eq!(image.to_rgba(palette, Some(&Colormap::identity()))[..4], [10, 20, 30, 255]);
+
+        let flat = FlatBank::load(&wad)?["FLAT"].to_image();
+        assert!(flat.is_opaque());
+        assert_eq!(flat.to_rgba(palette, None)[4 * 4095..], [7, 14, 21, 255]);
+
+        Ok(())
+    }
+}
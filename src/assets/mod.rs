@@ -1,12 +1,16 @@
 pub use assets::*;
+pub use colormap::*;
 pub use flat::*;
+pub use image::*;
 pub use palette::*;
 pub use patch::*;
 pub use texture::*;
 
 #[allow(clippy::module_inception)]
 mod assets;
+mod colormap;
 mod flat;
+mod image;
 mod palette;
 mod patch;
 mod texture;
@@ -1,10 +1,8 @@
-use std::convert::TryInto;
-use std::mem;
 use std::ops::Index;
 
-use bytes::{Buf, Bytes};
+use bytes::Buf;
 
-use crate::wad::{self, Cursor, Wad};
+use crate::wad::{self, Cursor, Diagnostics, Wad};
 
 /// A bank of color palettes from the `PLAYPAL` lump. The bank always has an [active] palette, which
 /// can be [switched] at any time.
@@ -20,14 +18,29 @@ pub struct PaletteBank {
 impl PaletteBank {
     /// Loads a bank of color palettes from the `PLAYPAL` lump.
     pub fn load(wad: &Wad) -> wad::Result<Self> {
-        let lump = wad.lump("PLAYPAL")?;
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
+
+    /// Loads a bank of color palettes from the `PLAYPAL` lump. If `diagnostics` is [lenient], a
+    /// partial palette at the end of the lump is ignored. There must be at least one complete
+    /// palette.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let lump = wad.lump_with("PLAYPAL", diagnostics)?;
         let mut cursor = lump.cursor();
 
         let mut palettes = Vec::with_capacity(lump.size() / PALETTE_BYTES);
-        cursor.need(1)?;
+        cursor.need(PALETTE_BYTES)?;
 
         while cursor.has_remaining() {
-            palettes.push(Palette::load(&mut cursor)?);
+            match Palette::load(&mut cursor) {
+                Ok(palette) => palettes.push(palette),
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    cursor.clear();
+                }
+            }
         }
 
         cursor.done()?;
@@ -40,6 +53,11 @@ impl PaletteBank {
         self.palettes.len()
     }
 
+    /// Looks up a palette number. The active palette doesn't change.
+    pub fn get(&self, index: usize) -> Option<&Palette> {
+        self.palettes.get(index)
+    }
+
     /// Returns the active palette.
     pub fn active(&self) -> &Palette {
         &self.palettes[self.active]
@@ -60,7 +78,7 @@ impl PaletteBank {
 /// A 256-color palette. Part of a [`PaletteBank`].
 #[derive(Debug, Clone)]
 pub struct Palette {
-    raw: Bytes,
+    colors: Vec<(u8, u8, u8)>,
 }
 
 const PALETTE_COLORS: usize = 256;
@@ -68,20 +86,24 @@ const PALETTE_BYTES: usize = 3 * PALETTE_COLORS;
 
 impl Palette {
     fn load(cursor: &mut Cursor) -> wad::Result<Self> {
-        cursor.need(PALETTE_BYTES)?;
-        let raw = cursor.split_to(PALETTE_BYTES);
-        Ok(Self { raw })
+        let colors = cursor.read_array::<[u8; 3]>(PALETTE_COLORS)?;
+        let colors = colors.into_iter().map(|[r, g, b]| (r, g, b)).collect();
+        Ok(Self { colors })
+    }
+
+    /// Looks up a color as red, green, blue, and alpha. Palette colors are always opaque.
+    pub fn rgba(&self, index: u8) -> [u8; 4] {
+        let (r, g, b) = self[index];
+        [r, g, b, u8::MAX]
     }
 }
 
 impl Index<u8> for Palette {
     type Output = (u8, u8, u8);
 
+    /// Looks up a color as a red, green, and blue tuple.
     fn index(&self, index: u8) -> &Self::Output {
-        let index: usize = index.into();
-        let rgb: &[u8; 3] = self.raw[index * 3..index * 3 + 3].try_into().unwrap();
-        // SAFETY: `[u8; 3]` and `(u8, u8, u8)` have the same size and layout.
-        unsafe { mem::transmute(rgb) }
+        &self.colors[usize::from(index)]
     }
 }
 
@@ -4,7 +4,8 @@ use std::ops::{Deref, Index};
 
 use bytes::{Buf, Bytes};
 
-use crate::wad::{self, Lump, Wad};
+use crate::assets::IndexedImage;
+use crate::wad::{self, Cursor, Diagnostics, Lump, LumpName, Record, Wad};
 
 /// A bank of patches from the `PNAMES` lump.
 ///
@@ -13,7 +14,7 @@ use crate::wad::{self, Lump, Wad};
 /// still lists all of the patches. It still loads because none of the textures in `TEXTURE1` use
 /// the missing patches.
 #[derive(Clone, Debug)]
-pub struct PatchBank(Vec<(String, Option<Patch>)>);
+pub struct PatchBank(Vec<(LumpName, Option<Patch>)>);
 
 impl PatchBank {
     /// Loads all the patches from a [`Wad`].
@@ -21,19 +22,30 @@ impl PatchBank {
     /// Patch names are listed in the `PNAMES` lump, and each patch is loaded from the lump of that
     /// name.
     pub fn load(wad: &Wad) -> wad::Result<Self> {
-        let lump = wad.lump("PNAMES")?;
-        let mut cursor = lump.cursor();
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
 
-        cursor.need(4)?;
-        let count = cursor.get_u32_le();
+    /// Loads all the patches from a [`Wad`]. If `diagnostics` is [lenient], malformed patches are
+    /// treated as missing.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let lump = wad.lump_with("PNAMES", diagnostics)?;
+        let mut cursor = lump.cursor();
 
+        let count: u32 = cursor.read()?;
         let mut patches = Vec::with_capacity(count.clamp(0, 1024) as usize);
-        cursor.need((count * 8).try_into().unwrap())?;
 
         for _ in 0..count {
-            let name = cursor.get_name();
-            let lump = wad.try_lump(&name)?;
-            let patch = lump.as_ref().map(Patch::load).transpose()?;
+            let name: LumpName = cursor.read()?;
+            let lump = wad.try_lump_with(name, diagnostics)?;
+            let patch = match lump.as_ref().map(Patch::load).transpose() {
+                Ok(patch) => patch,
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    None
+                }
+            };
             patches.push((name, patch));
         }
 
@@ -60,9 +72,9 @@ impl PatchBank {
     ///
     /// If the index is valid but the patch is missing, returns `Err(Some(name))` with the name of
     /// the missing patch. This happens with the shareware version of `doom.wad`.
-    pub fn get(&self, index: u16) -> Result<&Patch, Option<&str>> {
-        let (name, patch): &(String, Option<Patch>) = self.0.get(usize::from(index)).ok_or(None)?;
-        patch.as_ref().ok_or(Some(name))
+    pub fn get(&self, index: u16) -> Result<&Patch, Option<LumpName>> {
+        let (name, patch) = self.0.get(usize::from(index)).ok_or(None)?;
+        patch.as_ref().ok_or(Some(*name))
     }
 }
 
@@ -76,7 +88,7 @@ impl Index<u16> for PatchBank {
 }
 
 impl Deref for PatchBank {
-    type Target = Vec<(String, Option<Patch>)>;
+    type Target = Vec<(LumpName, Option<Patch>)>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -89,7 +101,7 @@ impl Deref for PatchBank {
 #[derive(Clone)]
 pub struct Patch {
     /// Patch name.
-    pub name: String,
+    pub name: LumpName,
 
     /// Width in pixels.
     pub width: u16,
@@ -115,54 +127,66 @@ struct Column {
     posts: Vec<Post>,
 }
 
+/// The header at the start of a patch lump.
+#[derive(Record)]
+struct PatchHeader {
+    width: u16,
+    height: u16,
+    y: i16,
+    x: i16,
+}
+
+/// A vertical run of opaque pixels in a [`Patch`] column or a masked [texture column]. The gaps
+/// between posts are transparent.
+///
+/// [texture column]: crate::assets::TextureColumn
 #[derive(Clone)]
-struct Post {
+pub struct Post {
     y_offset: u16,
     pixels: Bytes,
 }
 
+impl Post {
+    pub(super) fn new(y_offset: u16, pixels: Bytes) -> Self {
+        Self { y_offset, pixels }
+    }
+
+    /// The row of the first pixel.
+    pub fn y_offset(&self) -> u16 {
+        self.y_offset
+    }
+
+    /// The pixels' palette indices, top to bottom.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
 impl Patch {
     /// Loads a patch from a lump.
     pub fn load(lump: &Lump) -> wad::Result<Self> {
         let mut cursor = lump.cursor();
 
-        cursor.need(8)?;
-        let name = lump.name().to_owned();
-        let width = cursor.get_u16_le();
-        let height = cursor.get_u16_le();
-        let y = cursor.get_i16_le();
-        let x = cursor.get_i16_le();
-
-        // Read column offsets. The WAD is untrusted so clamp how much memory is pre-allocated.
-        let mut column_offsets = Vec::with_capacity(width.clamp(0, 512).into());
-        cursor.need((4 * width).into())?;
+        let name = lump.name();
+        let PatchHeader { width, height, y, x } = cursor.read()?;
 
-        for _ in 0..width {
-            column_offsets.push(cursor.get_u32_le());
-        }
-
-        cursor.clear();
-        cursor.done()?;
+        let column_offsets = cursor.read_array::<u32>(width.into())?;
 
         // Read columns. The WAD is untrusted so clamp how much memory is pre-allocated.
         let mut columns = Vec::with_capacity(width.clamp(0, 512).into());
         for offset in column_offsets {
-            columns.push(Self::read_column(lump, offset.try_into().unwrap())?);
+            columns.push(Self::read_column(cursor.at(offset.try_into().unwrap())?)?);
         }
 
         Ok(Self { name, width, height, x, y, columns })
     }
 
-    fn read_column(lump: &Lump, offset: usize) -> wad::Result<Column> {
-        let mut cursor = lump.cursor();
-        cursor.skip(offset)?;
-
+    fn read_column(mut cursor: Cursor) -> wad::Result<Column> {
         let mut posts = Vec::new();
         let mut last_y_offset = None;
 
         loop {
-            cursor.need(1)?;
-            let y_offset = match (cursor.get_u8() as u16, last_y_offset) {
+            let y_offset = match (u16::from(cursor.read::<u8>()?), last_y_offset) {
                 // The end of the column is marked by an offset of 255.
                 (255, _) => {
                     break;
@@ -182,8 +206,7 @@ impl Patch {
                 (y_offset, _) => y_offset,
             };
 
-            cursor.need(1)?;
-            let length = cursor.get_u8() as usize;
+            let length = usize::from(cursor.read::<u8>()?);
 
             cursor.need(length + 2)?;
             let _unused = cursor.get_u8();
@@ -199,6 +222,49 @@ impl Patch {
 
         Ok(Column { posts })
     }
+
+    /// The posts in column `x`, top to bottom. Returns `None` if `x` is out of range.
+    pub fn column(&self, x: u16) -> Option<&[Post]> {
+        Some(&self.columns.get(usize::from(x))?.posts)
+    }
+
+    /// Draws the patch by itself, ignoring its offsets.
+    pub fn to_image(&self) -> IndexedImage {
+        let mut image = IndexedImage::new(self.width, self.height);
+        self.draw(&mut image, 0, 0);
+        image
+    }
+
+    /// Draws the patch onto `image` with its top left corner at (`x`, `y`), clipping it to the
+    /// image's edges.
+    ///
+    /// Posts that start above the top edge are handled like vanilla's `R_DrawColumnInCache`: they
+    /// lose pixels off the bottom rather than the top. Textures with negative Y origins rely on
+    /// this.
+    pub(super) fn draw(&self, image: &mut IndexedImage, x: i32, y: i32) {
+        let width = i32::from(image.width());
+        let height = i32::from(image.height());
+
+        for (column, column_x) in self.columns.iter().zip(x..) {
+            if column_x < 0 || column_x >= width {
+                continue;
+            }
+
+            for post in &column.posts {
+                let mut top = y + i32::from(post.y_offset);
+                let mut count = post.pixels.len() as i32;
+                if top < 0 {
+                    count += top;
+                    top = 0;
+                }
+                count = count.min(height - top);
+
+                for (&color, pixel_y) in post.pixels.iter().take(count.max(0) as usize).zip(top..) {
+                    image.set(column_x as u16, pixel_y as u16, color);
+                }
+            }
+        }
+    }
 }
 
 impl fmt::Debug for Patch {
@@ -259,7 +325,7 @@ mod tests {
 
         assert_matches!(patches.get(161), Ok(patch) if patch.name == "WALL24_1");
         assert_matches!(patches.get(162), Ok(patch) if patch.name == "W94_1");
-        assert_matches!(patches.get(163), Err(Some("W104_1")));
-        assert_matches!(patches.get(164), Err(Some("DOOR9_2")));
+        assert_matches!(patches.get(163), Err(Some(name)) if name == "W104_1");
+        assert_matches!(patches.get(164), Err(Some(name)) if name == "DOOR9_2");
     }
 }
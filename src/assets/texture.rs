@@ -1,71 +1,141 @@
 use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::fmt;
 use std::ops::{Deref, Index};
+use std::sync::OnceLock;
 
-use bytes::Buf;
+use bytes::Bytes;
 
-use crate::wad::{self, Lump, Wad};
+use crate::assets::{IndexedImage, PatchBank, Post};
+use crate::wad::{
+    self, Cursor, Diagnostics, Lump, LumpName, MalformedKind, Record, ToLumpName, Wad,
+};
 
 /// A bank of [`Texture`]s from the `TEXTURE1` and `TEXTURE2` lumps, indexed by name.
 #[derive(Clone, Debug)]
-pub struct TextureBank(BTreeMap<String, Texture>);
+pub struct TextureBank(BTreeMap<LumpName, Texture>);
 
 impl TextureBank {
     /// Loads all the textures from a [`Wad`].
     ///
     /// Textures are listed in the `TEXTURE1` and `TEXTURE2` lumps.
     pub fn load(wad: &Wad) -> wad::Result<Self> {
+        Self::load_with(wad, &mut Diagnostics::strict())
+    }
+
+    /// Loads all the textures from a [`Wad`]. If `diagnostics` is [lenient], malformed textures
+    /// are skipped. `TEXTURE1` is still required.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
         let mut textures = BTreeMap::new();
 
-        for lump in Self::texture_lumps(wad)? {
-            Self::load_from(&lump, &mut textures)?;
+        for lump in Self::texture_lumps(wad, diagnostics)? {
+            Self::load_from(&lump, &mut textures, None, diagnostics)?;
         }
 
         Ok(Self(textures))
     }
 
-    fn texture_lumps(wad: &Wad) -> wad::Result<Vec<Lump>> {
-        let iter = Some(wad.lump("TEXTURE1")?).into_iter();
-        let iter = iter.chain(wad.try_lump("TEXTURE2")?);
+    /// Loads all the textures from a [`Wad`] and checks that every patch they use is in
+    /// `patches`. Textures that use out of range patch numbers or patches that are listed in
+    /// `PNAMES` but missing from the WAD are errors.
+    ///
+    /// If `diagnostics` is [lenient], malformed textures are skipped and bad patch references are
+    /// reported but kept. [Compositing] leaves them out.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [Compositing]: Texture::composite
+    pub fn load_resolved(
+        wad: &Wad,
+        patches: &PatchBank,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Self> {
+        let mut textures = BTreeMap::new();
+
+        for lump in Self::texture_lumps(wad, diagnostics)? {
+            Self::load_from(&lump, &mut textures, Some(patches), diagnostics)?;
+        }
+
+        Ok(Self(textures))
+    }
+
+    fn texture_lumps(wad: &Wad, diagnostics: &mut Diagnostics) -> wad::Result<Vec<Lump>> {
+        let iter = Some(wad.lump_with("TEXTURE1", diagnostics)?).into_iter();
+        let iter = iter.chain(wad.try_lump_with("TEXTURE2", diagnostics)?);
         Ok(iter.collect())
     }
 
-    fn load_from(lump: &Lump, textures: &mut BTreeMap<String, Texture>) -> wad::Result<()> {
-        let mut cursor = lump.cursor();
+    /// Loads the textures from a single `TEXTUREx` lump into `textures`. If `patches` is given,
+    /// the textures' patch references are checked against it.
+    pub(crate) fn load_from(
+        lump: &Lump,
+        textures: &mut BTreeMap<LumpName, Texture>,
+        patches: Option<&PatchBank>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<()> {
+        let cursor = lump.cursor();
+        let offsets = diagnostics.recover(Self::read_offsets(lump), Vec::new)?;
 
-        cursor.need(4)?;
-        let count = cursor.get_u32_le();
-
-        // Read texture offsets. The WAD is untrusted so clamp how much memory is pre-allocated.
-        // Don't worry about overflow converting from `u32` to `usize`. The wrong capacity won't
-        // affect correctness.
-        let mut offsets = Vec::with_capacity(count.clamp(0, 1024) as usize);
-        let need: usize = count
-            .checked_mul(4)
-            .ok_or_else(|| lump.error(format!("bad count {}", count)))?
-            .try_into()
-            .unwrap();
-        cursor.need(need)?;
-
-        for _ in 0..count {
-            offsets.push(cursor.get_u32_le());
+        // Read textures.
+        for offset in offsets {
+            let offset: usize = offset.try_into().unwrap();
+            let texture = match cursor.at(offset).and_then(Texture::load) {
+                Ok(texture) => texture,
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    continue;
+                }
+            };
+
+            if let Some(patches) = patches {
+                Self::check_patches(lump, offset, &texture, patches, diagnostics)?;
+            }
+            textures.insert(texture.name, texture);
         }
 
-        cursor.clear();
-        cursor.done()?;
+        Ok(())
+    }
 
-        // Read textures.
-        for offset in offsets {
-            let texture = Texture::load(lump, offset.try_into().unwrap())?;
-            textures.insert(texture.name.clone(), texture);
+    /// Checks that the patches a texture uses exist. `offset` is the texture's offset in `lump`.
+    fn check_patches(
+        lump: &Lump,
+        offset: usize,
+        texture: &Texture,
+        patches: &PatchBank,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<()> {
+        for (i, placement) in texture.patches.iter().enumerate() {
+            let desc = match patches.get(placement.patch) {
+                Ok(_) => continue,
+                Err(None) => format!(
+                    "texture {} uses patch #{}, but PNAMES only has {}",
+                    texture.name,
+                    placement.patch,
+                    patches.len()
+                ),
+                Err(Some(name)) => {
+                    format!("texture {} uses missing patch {}", texture.name, name)
+                }
+            };
+
+            let offset = offset + RawTexture::SIZE + i * RawPatchPlacement::SIZE;
+            diagnostics.report(lump.error_at(offset, MalformedKind::BadReference, desc))?;
         }
 
         Ok(())
     }
 
+    /// Reads the offsets of the textures in a `TEXTUREx` lump.
+    fn read_offsets(lump: &Lump) -> wad::Result<Vec<u32>> {
+        let mut cursor = lump.cursor();
+        let count: u32 = cursor.read()?;
+        cursor.read_array(count.try_into().unwrap())
+    }
+
     /// Looks up a texture name. Case insensitive.
-    pub fn get(&self, name: &str) -> Option<&Texture> {
-        self.0.get(&name.to_ascii_uppercase())
+    pub fn get(&self, name: impl ToLumpName) -> Option<&Texture> {
+        self.0.get(&name.to_lump_name()?)
     }
 }
 
@@ -78,8 +148,17 @@ impl Index<&str> for TextureBank {
     }
 }
 
+impl Index<LumpName> for TextureBank {
+    type Output = Texture;
+
+    /// Looks up a texture name.
+    fn index(&self, name: LumpName) -> &Self::Output {
+        self.get(name).expect("texture not found")
+    }
+}
+
 impl Deref for TextureBank {
-    type Target = BTreeMap<String, Texture>;
+    type Target = BTreeMap<LumpName, Texture>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -97,7 +176,7 @@ pub struct Texture {
     /// Name of the texture. Used by [sidedefs].
     ///
     /// [sidedefs]: crate::map::Sidedef
-    pub name: String,
+    pub name: LumpName,
 
     /// Total width in pixels.
     pub width: u16,
@@ -110,56 +189,240 @@ pub struct Texture {
 }
 
 impl Texture {
-    fn load(lump: &Lump, offset: usize) -> wad::Result<Self> {
-        let mut cursor = lump.cursor();
-        cursor.skip(offset)?;
-
-        cursor.need(22)?;
-        let name = cursor.get_name();
-        let _flags = cursor.get_u16_le();
-        let _unused = cursor.get_u16_le();
-        let width = cursor.get_u16_le();
-        let height = cursor.get_u16_le();
-        let _unused = cursor.get_u32_le();
-
-        let patch_count: usize = cursor.get_u16_le().into();
-        let mut patches = Vec::with_capacity(patch_count.clamp(0, 64));
-        cursor.need(patch_count * 10)?;
-
-        for _ in 0..patch_count {
-            let x = cursor.get_u16_le();
-            let y = cursor.get_u16_le();
-            let patch = cursor.get_u16_le();
-            let _unused = cursor.get_u16_le();
-            let _unused = cursor.get_u16_le();
-
-            patches.push(PatchPlacement { x, y, patch });
-        }
+    fn load(mut cursor: Cursor) -> wad::Result<Self> {
+        let RawTexture { name, width, height, patch_count, .. } = cursor.read()?;
+        let patches = cursor
+            .read_array::<RawPatchPlacement>(patch_count.into())?
+            .into_iter()
+            .map(|RawPatchPlacement { x, y, patch, .. }| PatchPlacement { x, y, patch })
+            .collect();
 
         cursor.clear();
         cursor.done()?;
 
         Ok(Self { name, width, height, patches })
     }
+
+    /// A list of patches and their X and Y offsets, in drawing order.
+    pub fn patches(&self) -> &[PatchPlacement] {
+        &self.patches
+    }
+
+    /// Draws the texture's patches in order onto a transparent canvas. Later patches overwrite
+    /// earlier ones where they overlap, and patches are clipped to the canvas like in vanilla.
+    /// Missing patches are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::assets::{PatchBank, TextureBank};
+    /// use dusty_room::wad::Wad;
+    ///
+    /// let wad = Wad::load("doom2.wad")?;
+    /// let patches = PatchBank::load(&wad)?;
+    /// let textures = TextureBank::load(&wad)?;
+    ///
+    /// let image = textures["EXITDOOR"].composite(&patches);
+    /// assert_eq!((image.width(), image.height()), (128, 72));
+    /// assert!(image.is_opaque());
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    pub fn composite(&self, patches: &PatchBank) -> IndexedImage {
+        let mut image = IndexedImage::new(self.width, self.height);
+
+        for placement in &self.patches {
+            if let Ok(patch) = patches.get(placement.patch) {
+                patch.draw(&mut image, placement.x.into(), placement.y.into());
+            }
+        }
+
+        image
+    }
+
+    /// Composites the texture and splits it into columns.
+    fn build_columns(&self, patches: &PatchBank) -> Vec<CachedColumn> {
+        let image = self.composite(patches);
+
+        (0..self.width)
+            .map(|x| {
+                let pixels: Vec<u8> =
+                    (0..self.height).map(|y| image.get(x, y).unwrap_or_default()).collect();
+                let pixels = Bytes::from(pixels);
+
+                // Split the opaque runs into posts.
+                let mut posts = Vec::new();
+                let mut y = 0;
+                while y < self.height {
+                    let start = y;
+                    while y < self.height && image.get(x, y).is_some() {
+                        y += 1;
+                    }
+                    if y > start {
+                        let range = usize::from(start)..usize::from(y);
+                        posts.push(Post::new(start, pixels.slice(range)));
+                    }
+                    y += 1;
+                }
+
+                // Like vanilla's `R_GenerateLookup`, a patch counts if it spans the column even if
+                // it has no pixels there.
+                let mut spanning = self.patches.iter().filter(|placement| {
+                    patches.get(placement.patch).is_ok_and(|patch| {
+                        let left = i32::from(placement.x);
+                        (left..left + i32::from(patch.width)).contains(&i32::from(x))
+                    })
+                });
+                let patch = match (spanning.next(), spanning.next()) {
+                    (Some(placement), None) => Some(placement.patch),
+                    _ => None,
+                };
+
+                CachedColumn { pixels, posts, patch }
+            })
+            .collect()
+    }
+}
+
+/// Composited [`Texture`] columns, for renderers that draw walls one column at a time.
+///
+/// Each texture is composited the first time one of its columns is needed, and its columns are
+/// kept for as long as the cache lives. The cache borrows the texture and patch banks it's built
+/// from, so it can't be handed mismatched patches later.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dusty_room::assets::{Assets, TextureColumns};
+/// use dusty_room::wad::Wad;
+///
+/// let wad = Wad::load("doom.wad")?;
+/// let assets = Assets::load(&wad)?;
+/// let columns = TextureColumns::new(&assets.texture_bank, &assets.patch_bank);
+///
+/// let column = columns.column("STARTAN3", -1).unwrap();
+/// assert_eq!(column.pixels().len(), usize::from(assets.texture_bank["STARTAN3"].height));
+/// #
+/// # Ok::<(), dusty_room::wad::Error>(())
+/// ```
+pub struct TextureColumns<'assets> {
+    textures: &'assets TextureBank,
+    patches: &'assets PatchBank,
+    columns: BTreeMap<LumpName, OnceLock<Vec<CachedColumn>>>,
 }
 
+struct CachedColumn {
+    pixels: Bytes,
+    posts: Vec<Post>,
+    patch: Option<u16>,
+}
+
+impl<'assets> TextureColumns<'assets> {
+    /// Creates an empty cache for the textures in `textures`, drawn with `patches`.
+    pub fn new(textures: &'assets TextureBank, patches: &'assets PatchBank) -> Self {
+        let columns = textures.keys().map(|&name| (name, OnceLock::new())).collect();
+        Self { textures, patches, columns }
+    }
+
+    /// Column `x` of a texture. `x` wraps around, so textures of any width tile correctly, not
+    /// just powers of two. Returns `None` if there's no such texture or it has no width.
+    pub fn column(&self, name: impl ToLumpName, x: i32) -> Option<TextureColumn<'_>> {
+        let name = name.to_lump_name()?;
+        let texture = self.textures.get(name)?;
+        if texture.width == 0 {
+            return None;
+        }
+
+        let columns = self.columns[&name].get_or_init(|| texture.build_columns(self.patches));
+        let column = &columns[x.rem_euclid(texture.width.into()) as usize];
+        Some(TextureColumn { pixels: &column.pixels, posts: &column.posts, patch: column.patch })
+    }
+}
+
+impl fmt::Debug for TextureColumns<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let Self { textures: _, patches: _, columns } = self;
+        let cached = columns.iter().filter(|(_, columns)| columns.get().is_some());
+
+        fmt.debug_struct("TextureColumns")
+            .field("cached", &cached.map(|(name, _)| name).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// One column of a [`Texture`], from [`TextureColumns::column`].
+#[derive(Clone, Copy, Debug)]
+pub struct TextureColumn<'texture> {
+    pixels: &'texture [u8],
+    posts: &'texture [Post],
+    patch: Option<u16>,
+}
+
+impl<'texture> TextureColumn<'texture> {
+    /// The column's palette indices, top to bottom. Transparent pixels are 0. Use this to draw
+    /// solid walls.
+    pub fn pixels(&self) -> &'texture [u8] {
+        self.pixels
+    }
+
+    /// The column's opaque runs. Use this to draw masked mid-textures, which have see-through
+    /// gaps.
+    pub fn posts(&self) -> &'texture [Post] {
+        self.posts
+    }
+
+    /// If exactly one patch spans this column, its patch number. Such columns can be drawn
+    /// straight from the patch's [column] like vanilla does.
+    ///
+    /// [column]: crate::assets::Patch::column
+    pub fn patch(&self) -> Option<u16> {
+        self.patch
+    }
+}
+
+/// A [patch] drawn at a particular offset on a [`Texture`].
+///
+/// [patch]: crate::assets::Patch
 #[derive(Clone, Debug)]
-struct PatchPlacement {
-    /// X offset of the patch on the texture's "canvas".
-    pub x: u16,
+pub struct PatchPlacement {
+    /// X offset of the patch on the texture's "canvas". Can be negative.
+    pub x: i16,
 
-    /// Y offset of the patch on the texture's "canvas".
-    pub y: u16,
+    /// Y offset of the patch on the texture's "canvas". Can be negative.
+    pub y: i16,
 
     /// Patch number to draw.
     pub patch: u16,
 }
 
+/// A texture's header as stored in a `TEXTUREx` lump. It's followed by `patch_count` patch
+/// placements.
+#[derive(Record)]
+struct RawTexture {
+    name: LumpName,
+    _flags: u16,
+    _unused: u16,
+    width: u16,
+    height: u16,
+    _column_directory: u32,
+    patch_count: u16,
+}
+
+/// A patch placement as stored in a `TEXTUREx` lump.
+#[derive(Record)]
+struct RawPatchPlacement {
+    x: i16,
+    y: i16,
+    patch: u16,
+    _step_dir: u16,
+    _colormap: u16,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::assets::PatchBank;
     use crate::wad::test::*;
+    use crate::wad::{WadBuilder, WadKind};
 
     #[test]
     fn load() {
@@ -184,4 +447,134 @@ mod tests {
         assert_eq!(exit_door.patches[3].y, 0);
         assert_eq!(patches.get(exit_door.patches[3].patch).unwrap().name, "T14_5");
     }
+
+    /// Builds a patch lump with one post per column.
+    fn patch_lump(height: u16, posts: &[(u8, &[u8])]) -> Vec<u8> {
+        let width = posts.len() as u16;
+        let mut lump = [width, height, 0, 0].to_bytes();
+        let mut offset = 8 + 4 * u32::from(width);
+        for (_, pixels) in posts {
+            offset.encode(&mut lump);
+            offset += pixels.len() as u32 + 5;
+        }
+        for (top, pixels) in posts {
+            lump.extend([*top, pixels.len() as u8, 0]);
+            lump.extend(*pixels);
+            lump.extend([0, 0xFF]);
+        }
+        lump
+    }
+
+    /// A WAD with a 3x2 texture named `DOOR` made of two real patches, one missing patch, and one
+    /// out of range patch number.
+    fn door_wad() -> wad::Result<Wad> {
+        let name = |name: &str| name.parse::<LumpName>().unwrap();
+        let placement = |x, y, patch| RawPatchPlacement { x, y, patch, _step_dir: 0, _colormap: 0 };
+
+        let mut texture1 = [1u32, 8].to_bytes();
+        RawTexture {
+            name: name("DOOR"),
+            _flags: 0,
+            _unused: 0,
+            width: 3,
+            height: 2,
+            _column_directory: 0,
+            patch_count: 5,
+        }
+        .encode(&mut texture1);
+        placement(-1, 0, 0).encode(&mut texture1);
+        placement(2, 0, 0).encode(&mut texture1);
+        placement(2, -1, 1).encode(&mut texture1);
+        placement(0, 0, 9).encode(&mut texture1);
+        placement(0, 0, 2).encode(&mut texture1);
+
+        let mut pnames = 3u32.to_bytes();
+        name("SQUARE").encode(&mut pnames);
+        name("STRIPE").encode(&mut pnames);
+        name("GONE").encode(&mut pnames);
+
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder
+            .lump("PNAMES", pnames)
+            .lump("TEXTURE1", texture1)
+            .lump("SQUARE", patch_lump(2, &[(0, &[1, 1]), (0, &[2, 2])]))
+            .lump("STRIPE", patch_lump(3, &[(0, &[5, 6, 7])]));
+        Wad::new(builder.build_file("door.wad")?)
+    }
+
+    #[test]
+    fn composite() -> wad::Result<()> {
+        let wad = door_wad()?;
+        let patches = PatchBank::load(&wad)?;
+        let textures = TextureBank::load(&wad)?;
+        let image = textures["DOOR"].composite(&patches);
+
+        // The first patch hangs off the left edge. The second is covered up by the third, which
+        // starts above the top edge and so is drawn from its first pixel. The bad patches are
+        // skipped.
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.pixels(), [2, 0, 5, 2, 0, 6]);
+        assert_eq!(image.mask(), [true, false, true, true, false, true]);
+        assert_eq!(image.get(1, 0), None);
+        assert!(!image.is_opaque());
+
+        let stripe = patches.get(1).unwrap().to_image();
+        assert_eq!(stripe.pixels(), [5, 6, 7]);
+        assert!(stripe.is_opaque());
+
+        Ok(())
+    }
+
+    #[test]
+    fn columns() -> wad::Result<()> {
+        let wad = door_wad()?;
+        let patches = PatchBank::load(&wad)?;
+        let textures = TextureBank::load(&wad)?;
+        let columns = TextureColumns::new(&textures, &patches);
+
+        let column = columns.column("DOOR", 0).unwrap();
+        assert_eq!(column.pixels(), [2, 2]);
+        assert_eq!(column.posts().len(), 1);
+        assert_eq!(column.patch(), Some(0));
+        assert_eq!(patches[0].column(1).unwrap()[0].pixels(), column.posts()[0].pixels());
+
+        let column = columns.column("DOOR", 1).unwrap();
+        assert_eq!(column.pixels(), [0, 0]);
+        assert!(column.posts().is_empty());
+        assert_eq!(column.patch(), None);
+
+        // Two patches overlap in the last column. Columns wrap around in both directions.
+        let column = columns.column("DOOR", -1).unwrap();
+        assert_eq!(column.pixels(), [5, 6]);
+        assert_eq!(column.patch(), None);
+        assert_eq!(columns.column("DOOR", 4).unwrap().pixels(), [0, 0]);
+        assert_eq!(columns.column("DOOR", 302).unwrap().posts()[0].y_offset(), 0);
+        assert!(columns.column("NOSUCH", 0).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve() -> wad::Result<()> {
+        let wad = door_wad()?;
+        let patches = PatchBank::load(&wad)?;
+
+        let err = TextureBank::load_resolved(&wad, &patches, &mut Diagnostics::strict());
+        assert_matches!(err, Err(err) if err.kind() == Some(MalformedKind::BadReference));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let textures = TextureBank::load_resolved(&wad, &patches, &mut diagnostics)?;
+        assert_eq!(textures["DOOR"].patches().len(), 5);
+
+        let warnings: Vec<_> = diagnostics.warnings().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            [
+                "door.wad: TEXTURE1 at offset 60: texture DOOR uses patch #9, but PNAMES only has 3",
+                "door.wad: TEXTURE1 at offset 70: texture DOOR uses missing patch GONE",
+            ]
+        );
+
+        Ok(())
+    }
 }
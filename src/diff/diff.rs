@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::diff::{semantic, Difference};
+use crate::wad::{self, DuplicatePolicy, Lump, LumpName, Namespace, ResolvedLump, Wad, WadFile};
+
+/// The differences between two WADs: which lumps were added, removed, moved, or modified. For
+/// lumps it understands--maps, `TEXTURE1` and `TEXTURE2`, `PNAMES`, and `PLAYPAL`--the diff also
+/// says what changed inside them.
+///
+/// Lumps are matched up by namespace and name, and map data lumps by the map they belong to. The
+/// diff works on the [resolved directory], so when comparing [`Wad`]s only the lumps in effect
+/// are compared.
+///
+/// `WadDiff`'s [`Display`] impl produces a human-readable report.
+///
+/// [resolved directory]: Wad::resolved_directory
+/// [`Display`]: fmt::Display
+#[derive(Clone, Debug)]
+pub struct WadDiff {
+    lumps: Vec<LumpDiff>,
+}
+
+/// A change to a single lump. See [`WadDiff`].
+#[derive(Clone, Debug)]
+pub struct LumpDiff {
+    /// The namespace the lump is in.
+    pub namespace: Namespace,
+
+    /// The map the lump belongs to, if it's one of a map's data lumps.
+    pub map: Option<LumpName>,
+
+    /// The lump name.
+    pub name: LumpName,
+
+    /// What happened to it.
+    pub change: LumpChange,
+}
+
+/// What happened to a lump. See [`LumpDiff`].
+#[derive(Clone, Debug)]
+pub enum LumpChange {
+    /// The lump is new.
+    Added(Lump),
+
+    /// The lump is gone.
+    Removed(Lump),
+
+    /// The lump is in a different position relative to the lumps around it. Its contents may have
+    /// changed as well, which is reported separately.
+    Moved {
+        /// The lump in the old WAD.
+        old: Lump,
+
+        /// The lump in the new WAD.
+        new: Lump,
+    },
+
+    /// The lump's contents changed.
+    Modified {
+        /// The lump in the old WAD.
+        old: Lump,
+
+        /// The lump in the new WAD.
+        new: Lump,
+
+        /// What changed inside the lump. Empty if the diff doesn't understand the lump.
+        details: Vec<Difference>,
+    },
+}
+
+impl WadDiff {
+    /// Compares two layered WADs, looking only at the lumps in effect.
+    ///
+    /// # Errors
+    ///
+    /// Fails if either WAD's [resolved directory] can't be built.
+    ///
+    /// [resolved directory]: Wad::resolved_directory
+    pub fn wads(old: &Wad, new: &Wad) -> wad::Result<Self> {
+        Ok(Self::new(old.resolved_directory()?, new.resolved_directory()?))
+    }
+
+    /// Compares two files. Duplicate lump names are resolved the way vanilla DOOM does, with the
+    /// last copy taking precedence.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if either file's namespace markers are unbalanced.
+    pub fn files(old: &Arc<WadFile>, new: &Arc<WadFile>) -> wad::Result<Self> {
+        let wad = |file: &Arc<WadFile>| -> wad::Result<Wad> {
+            Ok(Wad::new(Arc::clone(file))?.with_duplicate_policy(DuplicatePolicy::Last))
+        };
+        Self::wads(&wad(old)?, &wad(new)?)
+    }
+
+    fn new(old: Vec<ResolvedLump>, new: Vec<ResolvedLump>) -> Self {
+        let key = |entry: &ResolvedLump| (entry.namespace(), entry.map(), entry.name());
+        let old_positions: HashMap<_, usize> =
+            old.iter().enumerate().map(|(position, entry)| (key(entry), position)).collect();
+        let new_keys: HashSet<_> = new.iter().map(key).collect();
+
+        let diff = |entry: &ResolvedLump, change| LumpDiff {
+            namespace: entry.namespace(),
+            map: entry.map(),
+            name: entry.name(),
+            change,
+        };
+        let mut lumps = Vec::new();
+
+        for entry in &old {
+            if !new_keys.contains(&key(entry)) {
+                lumps.push(diff(entry, LumpChange::Removed(entry.lump().clone())));
+            }
+        }
+
+        // The lumps that kept their relative order are the longest run of common lumps whose old
+        // positions are increasing. Everything else moved.
+        let positions: Vec<Option<usize>> =
+            new.iter().map(|entry| old_positions.get(&key(entry)).copied()).collect();
+        let common: Vec<usize> = positions.iter().flatten().copied().collect();
+        let in_order: HashSet<usize> =
+            longest_increasing(&common).into_iter().map(|index| common[index]).collect();
+
+        for (entry, position) in new.iter().zip(positions) {
+            let new_lump = entry.lump().clone();
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    lumps.push(diff(entry, LumpChange::Added(new_lump)));
+                    continue;
+                }
+            };
+            let old_lump = old[position].lump().clone();
+
+            if !in_order.contains(&position) {
+                let change = LumpChange::Moved { old: old_lump.clone(), new: new_lump.clone() };
+                lumps.push(diff(entry, change));
+            }
+
+            if old_lump.data() != new_lump.data() {
+                let details =
+                    semantic::diff_lump(entry.namespace(), entry.map(), &old_lump, &new_lump);
+                let change = LumpChange::Modified { old: old_lump, new: new_lump, details };
+                lumps.push(diff(entry, change));
+            }
+        }
+
+        Self { lumps }
+    }
+
+    /// The changed lumps. Removed lumps are listed first in their old order, followed by the rest
+    /// in their new order.
+    pub fn lumps(&self) -> &[LumpDiff] {
+        &self.lumps
+    }
+
+    /// Returns `true` if the WADs have the same lumps in the same order with the same contents.
+    pub fn is_empty(&self) -> bool {
+        self.lumps.is_empty()
+    }
+}
+
+/// Finds the longest strictly increasing subsequence. Returns the indices of its elements.
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    // `tails[k]` is the index of the smallest value that ends an increasing run of length `k + 1`.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = Vec::with_capacity(values.len());
+
+    for (index, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        previous.push(length.checked_sub(1).map(|length| tails[length]));
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut run = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        run.push(index);
+        next = previous[index];
+    }
+    run.reverse();
+    run
+}
+
+impl fmt::Display for WadDiff {
+    /// Writes one line per lump change, with the details of modified lumps indented below them.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for lump in &self.lumps {
+            writeln!(fmt, "{}", lump)?;
+
+            if let LumpChange::Modified { details, .. } = &lump.change {
+                for detail in details {
+                    writeln!(fmt, "    {}", detail)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for LumpDiff {
+    /// Writes a one-line summary. Details of modified lumps are not included.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let Self { namespace, map, name, change } = self;
+
+        match change {
+            LumpChange::Added(_) => write!(fmt, "added ")?,
+            LumpChange::Removed(_) => write!(fmt, "removed ")?,
+            LumpChange::Moved { .. } => write!(fmt, "moved ")?,
+            LumpChange::Modified { .. } => write!(fmt, "modified ")?,
+        }
+
+        if let Some(map) = map {
+            write!(fmt, "{} ", map)?;
+        }
+        write!(fmt, "{}", name)?;
+        if *namespace != Namespace::Global {
+            write!(fmt, " ({})", namespace)?;
+        }
+
+        match change {
+            LumpChange::Added(lump) | LumpChange::Removed(lump) => {
+                write!(fmt, " at #{}", lump.index())
+            }
+            LumpChange::Moved { old, new } => {
+                write!(fmt, " from #{} to #{}", old.index(), new.index())
+            }
+            LumpChange::Modified { old, new, .. } => {
+                write!(fmt, ", {} → {} bytes", old.size(), new.size())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{WadBuilder, WadKind};
+
+    #[test]
+    fn files() -> wad::Result<()> {
+        let mut old = WadBuilder::new(WadKind::Pwad);
+        old.lump("DEHACKED", vec![1; 10]).lump("DEMO1", vec![2; 10]).lump("DEMO2", vec![3; 10]);
+        old.marker("FF_START").lump("FLAT1", vec![4; 4096]).marker("FF_END");
+        old.map("MAP01", [("THINGS", vec![5; 10]), ("SECTORS", vec![0; 26])]);
+
+        let mut new = WadBuilder::new(WadKind::Pwad);
+        new.lump("DEMO2", vec![3; 10]).lump("DEHACKED", vec![1; 10]);
+        new.marker("FF_START").lump("FLAT1", vec![4; 4096]).lump("FLAT2", vec![6; 4096]);
+        new.marker("FF_END");
+        new.map("MAP01", [("THINGS", vec![5; 10]), ("SECTORS", [vec![24], vec![0; 25]].concat())]);
+
+        let old = old.build_file("old.wad")?;
+        let new = new.build_file("new.wad")?;
+
+        assert!(WadDiff::files(&old, &old)?.is_empty());
+
+        let diff = WadDiff::files(&old, &new)?;
+        assert_matches!(
+            diff.lumps(),
+            [
+                LumpDiff { change: LumpChange::Removed(demo1), .. },
+                LumpDiff { change: LumpChange::Moved { .. }, .. },
+                LumpDiff { change: LumpChange::Added(flat2), .. },
+                LumpDiff { change: LumpChange::Modified { details, .. }, map: Some(_), .. },
+            ] if demo1.name() == "DEMO1" && flat2.name() == "FLAT2" && details.len() == 1
+        );
+        assert_eq!(
+            diff.to_string(),
+            "\
+removed DEMO1 at #1
+moved DEMO2 from #2 to #0
+added FLAT2 (flats) at #4
+modified MAP01 SECTORS, 26 → 26 bytes
+    sector #0 floor height: 0 → 24
+",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn longest_increasing() {
+        assert_eq!(super::longest_increasing(&[]), Vec::<usize>::new());
+        assert_eq!(super::longest_increasing(&[0, 1, 2]), [0, 1, 2]);
+        assert_eq!(super::longest_increasing(&[1, 0, 2]), [1, 2]);
+        assert_eq!(super::longest_increasing(&[3, 0, 1, 4, 2]), [1, 2, 4]);
+    }
+}
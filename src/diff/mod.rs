@@ -0,0 +1,24 @@
+//! Compare two WADs and report what changed between them.
+//!
+//! # Examples
+//!
+//! See what changed between two releases of a PWAD:
+//!
+//! ```no_run
+//! use dusty_room::diff::WadDiff;
+//! use dusty_room::wad::WadFile;
+//!
+//! let old = WadFile::load("killer-1.0.wad")?;
+//! let new = WadFile::load("killer-1.1.wad")?;
+//!
+//! print!("{}", WadDiff::files(&old, &new)?);
+//! #
+//! # Ok::<(), dusty_room::wad::Error>(())
+//! ```
+
+pub use diff::*;
+pub use semantic::*;
+
+#[allow(clippy::module_inception)]
+mod diff;
+mod semantic;
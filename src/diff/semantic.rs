@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bytes::Buf;
+
+use crate::assets::{PatchPlacement, Texture, TextureBank};
+use crate::map::{RawLinedef, RawSector, RawSidedef, RawThing, Vertex};
+use crate::wad::{self, Diagnostics, Lump, LumpName, MalformedKind, Namespace, Record};
+
+/// A single change inside a lump that the diff understands, such as a map's sectors or the
+/// textures in `TEXTURE1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    /// Something new, like a sector or a texture.
+    Added(String),
+
+    /// Something that's gone.
+    Removed(String),
+
+    /// A value that changed.
+    Changed {
+        /// What changed, for example `sector #12 floor height`.
+        subject: String,
+
+        /// The old value.
+        old: String,
+
+        /// The new value.
+        new: String,
+    },
+
+    /// One of the versions couldn't be parsed, so its contents weren't compared. Holds the
+    /// parse error.
+    Unparsed(String),
+}
+
+impl Difference {
+    fn changed(subject: String, old: impl fmt::Display, new: impl fmt::Display) -> Self {
+        Self::Changed { subject, old: old.to_string(), new: new.to_string() }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Added(subject) => write!(fmt, "{} added", subject),
+            Self::Removed(subject) => write!(fmt, "{} removed", subject),
+            Self::Changed { subject, old, new } => write!(fmt, "{}: {} → {}", subject, old, new),
+            Self::Unparsed(error) => write!(fmt, "contents not compared: {}", error),
+        }
+    }
+}
+
+/// A map lump record that the diff compares field by field.
+trait MapRecord: Record {
+    /// The lump holding the records, like `SECTORS`.
+    const LUMP: &'static str;
+
+    /// What to call a single record, like `sector`.
+    const NOUN: &'static str;
+
+    /// Each field's label and formatted value.
+    fn fields(&self) -> Vec<(&'static str, String)>;
+}
+
+impl MapRecord for RawThing {
+    const LUMP: &'static str = "THINGS";
+    const NOUN: &'static str = "thing";
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("x", self.x.to_string()),
+            ("y", self.y.to_string()),
+            ("angle", self.angle.to_string()),
+            ("type", self.thing_type.to_string()),
+            ("flags", self.flags.to_string()),
+        ]
+    }
+}
+
+impl MapRecord for RawLinedef {
+    const LUMP: &'static str = "LINEDEFS";
+    const NOUN: &'static str = "linedef";
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("start vertex", self.start_vertex.to_string()),
+            ("end vertex", self.end_vertex.to_string()),
+            ("flags", self.flags.to_string()),
+            ("special", self.types.to_string()),
+            ("tag", self.tag.to_string()),
+            ("right sidedef", self.right_sidedef.to_string()),
+            ("left sidedef", self.left_sidedef.to_string()),
+        ]
+    }
+}
+
+impl MapRecord for RawSidedef {
+    const LUMP: &'static str = "SIDEDEFS";
+    const NOUN: &'static str = "sidedef";
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("x offset", self.x_offset.to_string()),
+            ("y offset", self.y_offset.to_string()),
+            ("upper texture", self.upper_texture.to_string()),
+            ("lower texture", self.lower_texture.to_string()),
+            ("middle texture", self.middle_texture.to_string()),
+            ("sector", self.sector.to_string()),
+        ]
+    }
+}
+
+impl MapRecord for Vertex {
+    const LUMP: &'static str = "VERTEXES";
+    const NOUN: &'static str = "vertex";
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("x", self.x.to_string()), ("y", self.y.to_string())]
+    }
+}
+
+impl MapRecord for RawSector {
+    const LUMP: &'static str = "SECTORS";
+    const NOUN: &'static str = "sector";
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("floor height", self.floor_height.to_string()),
+            ("ceiling height", self.ceiling_height.to_string()),
+            ("floor flat", self.floor_flat.to_string()),
+            ("ceiling flat", self.ceiling_flat.to_string()),
+            ("light level", self.light_level.to_string()),
+            ("special", self.special_type.to_string()),
+            ("tag", self.tag.to_string()),
+        ]
+    }
+}
+
+/// Compares the records in two versions of a map lump.
+fn diff_records<R: MapRecord>(old: &Lump, new: &Lump) -> wad::Result<Vec<Difference>> {
+    let read = |lump: &Lump| -> wad::Result<Vec<R>> {
+        let mut records = Vec::with_capacity(lump.size() / R::SIZE);
+        let mut cursor = lump.cursor();
+        while cursor.has_remaining() {
+            records.push(cursor.read()?);
+        }
+        cursor.done()?;
+        Ok(records)
+    };
+    let old = read(old)?;
+    let new = read(new)?;
+    let mut differences = Vec::new();
+
+    for number in 0..old.len().max(new.len()) {
+        let subject = format!("{} #{}", R::NOUN, number);
+
+        match (old.get(number), new.get(number)) {
+            (Some(old), Some(new)) => {
+                for ((label, old), (_, new)) in old.fields().into_iter().zip(new.fields()) {
+                    if old != new {
+                        let subject = format!("{} {}", subject, label);
+                        differences.push(Difference::changed(subject, old, new));
+                    }
+                }
+            }
+            (Some(_), None) => differences.push(Difference::Removed(subject)),
+            (None, Some(_)) => differences.push(Difference::Added(subject)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    Ok(differences)
+}
+
+/// Compares two versions of a lump that the diff understands. Returns an empty list if the lump
+/// isn't one it understands, or a single [`Difference::Unparsed`] if either version can't be
+/// parsed.
+pub(super) fn diff_lump(
+    namespace: Namespace,
+    map: Option<LumpName>,
+    old: &Lump,
+    new: &Lump,
+) -> Vec<Difference> {
+    let name = new.name();
+
+    let result = match (namespace, map) {
+        (Namespace::Global, Some(_)) if name == RawThing::LUMP => {
+            diff_records::<RawThing>(old, new)
+        }
+        (Namespace::Global, Some(_)) if name == RawLinedef::LUMP => {
+            diff_records::<RawLinedef>(old, new)
+        }
+        (Namespace::Global, Some(_)) if name == RawSidedef::LUMP => {
+            diff_records::<RawSidedef>(old, new)
+        }
+        (Namespace::Global, Some(_)) if name == Vertex::LUMP => diff_records::<Vertex>(old, new),
+        (Namespace::Global, Some(_)) if name == RawSector::LUMP => {
+            diff_records::<RawSector>(old, new)
+        }
+
+        (Namespace::Global, None) if name == "TEXTURE1" || name == "TEXTURE2" => {
+            diff_textures(old, new)
+        }
+        (Namespace::Global, None) if name == "PNAMES" => diff_patch_names(old, new),
+        (Namespace::Global, None) if name == "PLAYPAL" => diff_palettes(old, new),
+
+        _ => Ok(Vec::new()),
+    };
+
+    result.unwrap_or_else(|err| vec![Difference::Unparsed(err.to_string())])
+}
+
+fn diff_textures(old: &Lump, new: &Lump) -> wad::Result<Vec<Difference>> {
+    let read = |lump| -> wad::Result<BTreeMap<LumpName, Texture>> {
+        let mut textures = BTreeMap::new();
+        TextureBank::load_from(lump, &mut textures, None, &mut Diagnostics::strict())?;
+        Ok(textures)
+    };
+    let old = read(old)?;
+    let new = read(new)?;
+    let mut differences = Vec::new();
+
+    for name in old.keys().chain(new.keys().filter(|name| !old.contains_key(name))) {
+        let subject = format!("texture {}", name);
+
+        let (old, new) = match (old.get(name), new.get(name)) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(_), None) => {
+                differences.push(Difference::Removed(subject));
+                continue;
+            }
+            (None, _) => {
+                differences.push(Difference::Added(subject));
+                continue;
+            }
+        };
+
+        if old.width != new.width {
+            let subject = format!("{} width", subject);
+            differences.push(Difference::changed(subject, old.width, new.width));
+        }
+        if old.height != new.height {
+            let subject = format!("{} height", subject);
+            differences.push(Difference::changed(subject, old.height, new.height));
+        }
+
+        let (old, new) = (old.patches(), new.patches());
+        if old.len() != new.len() {
+            let subject = format!("{} patch count", subject);
+            differences.push(Difference::changed(subject, old.len(), new.len()));
+            continue;
+        }
+        for (number, (old, new)) in old.iter().zip(new).enumerate() {
+            if (old.x, old.y, old.patch) != (new.x, new.y, new.patch) {
+                let subject = format!("{} patch #{}", subject, number);
+                let show = |p: &PatchPlacement| format!("patch #{} at ({}, {})", p.patch, p.x, p.y);
+                differences.push(Difference::changed(subject, show(old), show(new)));
+            }
+        }
+    }
+
+    Ok(differences)
+}
+
+fn diff_patch_names(old: &Lump, new: &Lump) -> wad::Result<Vec<Difference>> {
+    let read = |lump: &Lump| -> wad::Result<Vec<LumpName>> {
+        let mut cursor = lump.cursor();
+        let count = cursor.read::<u32>()?;
+        let names = cursor.read_array::<LumpName>(count as usize)?;
+        cursor.done()?;
+
+        Ok(names)
+    };
+    let old = read(old)?;
+    let new = read(new)?;
+    let mut differences = Vec::new();
+
+    for number in 0..old.len().max(new.len()) {
+        let subject = format!("patch name #{}", number);
+
+        match (old.get(number), new.get(number)) {
+            (Some(old), Some(new)) if old != new => {
+                differences.push(Difference::changed(subject, old, new));
+            }
+            (Some(_), Some(_)) => {}
+            (Some(old), None) => {
+                differences.push(Difference::Removed(format!("{} {}", subject, old)))
+            }
+            (None, Some(new)) => {
+                differences.push(Difference::Added(format!("{} {}", subject, new)))
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    Ok(differences)
+}
+
+fn diff_palettes(old: &Lump, new: &Lump) -> wad::Result<Vec<Difference>> {
+    const PALETTE_BYTES: usize = 3 * 256;
+
+    for lump in [old, new] {
+        if lump.size() % PALETTE_BYTES != 0 {
+            let desc = format!("size {} isn't a multiple of {}", lump.size(), PALETTE_BYTES);
+            return Err(lump.error(MalformedKind::BadValue, desc));
+        }
+    }
+
+    let (old, new) = (old.data(), new.data());
+
+    let old: Vec<&[u8]> = old.chunks(PALETTE_BYTES).collect();
+    let new: Vec<&[u8]> = new.chunks(PALETTE_BYTES).collect();
+    let mut differences = Vec::new();
+
+    for number in 0..old.len().max(new.len()) {
+        let subject = format!("palette #{}", number);
+
+        let (old, new) = match (old.get(number), new.get(number)) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(_), None) => {
+                differences.push(Difference::Removed(subject));
+                continue;
+            }
+            (None, _) => {
+                differences.push(Difference::Added(subject));
+                continue;
+            }
+        };
+
+        for (color, (old, new)) in old.chunks(3).zip(new.chunks(3)).enumerate() {
+            if old != new {
+                let subject = format!("{} color {}", subject, color);
+                let show = |rgb: &[u8]| format!("({}, {}, {})", rgb[0], rgb[1], rgb[2]);
+                differences.push(Difference::changed(subject, show(old), show(new)));
+            }
+        }
+    }
+
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{WadBuilder, WadKind};
+
+    fn sector(floor: i16, flat: &str) -> Vec<u8> {
+        RawSector {
+            floor_height: floor,
+            ceiling_height: 128,
+            floor_flat: LumpName::new(flat).unwrap(),
+            ceiling_flat: LumpName::new("CEIL1").unwrap(),
+            light_level: 160,
+            special_type: 0,
+            tag: 0,
+        }
+        .to_bytes()
+    }
+
+    /// A texture's name, width, and patches as `(x, y, patch)` tuples.
+    type TextureDef<'a> = (&'a str, u16, &'a [(u16, u16, u16)]);
+
+    fn texture1(textures: &[TextureDef]) -> Vec<u8> {
+        let header_size = 4 + 4 * textures.len();
+        let mut raw = (textures.len() as u32).to_bytes();
+        let mut body = Vec::new();
+
+        for &(name, width, patches) in textures {
+            ((header_size + body.len()) as u32).encode(&mut raw);
+
+            LumpName::new(name).unwrap().encode(&mut body);
+            // Flags, unused, width, height, column directory, and patch count.
+            [0, 0, width, 128, 0, 0, patches.len() as u16].encode(&mut body);
+            for &(x, y, patch) in patches {
+                // Step direction and colormap are unused.
+                [x, y, patch, 0, 0].encode(&mut body);
+            }
+        }
+
+        raw.extend(body);
+        raw
+    }
+
+    fn lump(name: &str, data: Vec<u8>) -> Lump {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump(name, data);
+        builder.build_file("test.wad").unwrap().lump(name).unwrap()
+    }
+
+    fn diff(map: Option<&str>, old: &Lump, new: &Lump) -> Vec<String> {
+        let map = map.map(|map| map.parse().unwrap());
+        diff_lump(Namespace::Global, map, old, new).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn sectors() {
+        let old = lump("SECTORS", [sector(0, "FLOOR1"), sector(8, "FLOOR1")].concat());
+        let new = lump(
+            "SECTORS",
+            [sector(0, "FLOOR1"), sector(24, "NUKAGE1"), sector(0, "FLOOR1")].concat(),
+        );
+
+        assert_eq!(
+            diff(Some("MAP01"), &old, &new),
+            [
+                "sector #1 floor height: 8 → 24",
+                "sector #1 floor flat: FLOOR1 → NUKAGE1",
+                "sector #2 added",
+            ]
+        );
+        assert_eq!(diff(Some("MAP01"), &new, &old).last().unwrap(), "sector #2 removed");
+
+        // Not part of a map, so it's not parsed.
+        assert_eq!(diff(None, &old, &new), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unparsed() {
+        let old = lump("SECTORS", sector(0, "FLOOR1"));
+        let new = lump("SECTORS", [sector(0, "FLOOR1"), vec![0; 5]].concat());
+        let map = Some("MAP01".parse().unwrap());
+
+        assert_matches!(
+            diff_lump(Namespace::Global, map, &old, &new)[..],
+            [Difference::Unparsed(_)]
+        );
+    }
+
+    #[test]
+    fn unparsed_palette() {
+        let old = lump("PLAYPAL", vec![0; 768]);
+        let new = lump("PLAYPAL", vec![0; 770]);
+
+        assert_matches!(
+            diff_lump(Namespace::Global, None, &old, &new)[..],
+            [Difference::Unparsed(_)]
+        );
+    }
+
+    #[test]
+    fn textures() {
+        let old = lump(
+            "TEXTURE1",
+            texture1(&[("STARTAN3", 64, &[(0, 0, 1)]), ("OLD", 64, &[]), ("SAME", 64, &[])]),
+        );
+        let new = lump(
+            "TEXTURE1",
+            texture1(&[("STARTAN3", 128, &[(0, 8, 1)]), ("SAME", 64, &[]), ("NEW", 64, &[])]),
+        );
+
+        assert_eq!(
+            diff(None, &old, &new),
+            [
+                "texture OLD removed",
+                "texture STARTAN3 width: 64 → 128",
+                "texture STARTAN3 patch #0: patch #1 at (0, 0) → patch #1 at (0, 8)",
+                "texture NEW added",
+            ]
+        );
+    }
+
+    #[test]
+    fn patch_names_and_palettes() {
+        let pnames = |names: &[&str]| {
+            let mut raw = (names.len() as u32).to_bytes();
+            names.iter().for_each(|name| LumpName::new(name).unwrap().encode(&mut raw));
+            raw
+        };
+        let old = lump("PNAMES", pnames(&["WALL00_1", "WALL00_2"]));
+        let new = lump("PNAMES", pnames(&["WALL00_1", "W94_1", "DOOR2_1"]));
+        assert_eq!(
+            diff(None, &old, &new),
+            ["patch name #1: WALL00_2 → W94_1", "patch name #2 DOOR2_1 added"]
+        );
+
+        let mut palette = vec![0; 768];
+        let old = lump("PLAYPAL", palette.clone());
+        palette[3..6].copy_from_slice(&[255, 0, 0]);
+        let new = lump("PLAYPAL", [palette.clone(), palette].concat());
+        assert_eq!(
+            diff(None, &old, &new),
+            ["palette #0 color 1: (0, 0, 0) → (255, 0, 0)", "palette #1 added"]
+        );
+    }
+}
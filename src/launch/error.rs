@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+use crate::wad;
+
+/// A specialized [`Result`] type for [`launch`] operations.
+///
+/// [`Result`]: std::result::Result
+/// [`launch`]: crate::launch
+pub type Result<T> = std::result::Result<T, LaunchError>;
+
+/// The error type when parsing command lines and loading the resources they name.
+#[derive(Error, Debug)]
+pub enum LaunchError {
+    /// The command line is malformed, such as an option missing its argument.
+    #[error("{0}")]
+    Usage(String),
+
+    /// A resource couldn't be found or loaded.
+    #[error(transparent)]
+    Wad(#[from] wad::Error),
+}
@@ -0,0 +1,47 @@
+//! Parse source port command lines and load the resources they name.
+//!
+//! Launchers and tools accept the same resource options as Chocolate Doom:
+//!
+//! * `-iwad <file>`: the IWAD. A bare file name is looked for in the [IWAD search path]. If it's
+//!   not given, the first IWAD in the search path is used.
+//! * `-file <files...>`: PWADs to load. A PWAD's `F_START`/`F_END` block replaces the flats from
+//!   earlier files, and likewise for sprites and patches.
+//! * `-merge <files...>`: PWADs to merge. Their flats, sprites, and patches are added to the
+//!   earlier files'. They're loaded before any `-file` PWADs.
+//! * `-deh <files...>` or `-bex <files...>`: DeHackEd patches.
+//! * `-warp <map>` or `-warp <episode> <map>`: the map to start on.
+//! * `@<file>`: a response file containing more arguments.
+//!
+//! Other options are ignored so that programs can mix in their own.
+//!
+//! # Differences from Chocolate Doom
+//!
+//! `-merge` sprites are merged by lump name only. Chocolate Doom also lets a merged sprite replace
+//! IWAD sprites that draw the same frame and rotation under a different name; see
+//! [`Wad::namespace`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use dusty_room::launch::ResourcePlan;
+//!
+//! let plan = ResourcePlan::parse(std::env::args().skip(1))?;
+//! let resources = plan.load()?;
+//!
+//! println!("{} DeHackEd patches", resources.dehacked.len());
+//! if let Some(map) = resources.warp {
+//!     println!("warping to {}", map);
+//! }
+//! #
+//! # Ok::<(), dusty_room::launch::LaunchError>(())
+//! ```
+//!
+//! [IWAD search path]: crate::wad::IwadLocator
+//! [`Wad::namespace`]: crate::wad::Wad::namespace
+
+pub use error::*;
+pub use plan::*;
+
+mod error;
+mod plan;
+mod response;
@@ -0,0 +1,342 @@
+use std::fmt;
+use std::fs;
+use std::iter::Peekable;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+
+use crate::launch::{self, response, LaunchError};
+use crate::wad::{self, GameMission, IwadLocator, LumpName, Wad};
+
+/// The resources named on a command line, in the order they'll be loaded. See the [module
+/// documentation] for the options it understands.
+///
+/// [module documentation]: crate::launch
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourcePlan {
+    /// The `-iwad` argument, if given.
+    pub iwad: Option<PathBuf>,
+
+    /// PWADs from `-merge`.
+    pub merge: Vec<PathBuf>,
+
+    /// PWADs from `-file`.
+    pub files: Vec<PathBuf>,
+
+    /// DeHackEd patches from `-deh` and `-bex`.
+    pub dehacked: Vec<PathBuf>,
+
+    /// The `-warp` argument, if given.
+    pub warp: Option<Warp>,
+}
+
+/// A `-warp` argument. Its meaning depends on the game: DOOM II takes a map number, while
+/// episodic games take an episode and a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Warp {
+    first: u32,
+    second: Option<u32>,
+}
+
+/// The loaded resources from a [`ResourcePlan`].
+#[derive(Clone, Debug)]
+pub struct Resources {
+    /// The IWAD with all of the PWADs layered on top.
+    pub wad: Wad,
+
+    /// The contents of each DeHackEd patch, in command-line order.
+    pub dehacked: Vec<(PathBuf, Bytes)>,
+
+    /// The marker lump of the map to warp to, if any.
+    pub warp: Option<LumpName>,
+}
+
+impl ResourcePlan {
+    /// Parses a command line. The program name should not be included. Response files are
+    /// expanded and options are matched case-insensitively, as in vanilla. Unrecognized options
+    /// are ignored.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if an option is missing its arguments, or if a response file can't be read.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> launch::Result<Self> {
+        let mut plan = Self::default();
+        let mut args = response::expand(args)?.into_iter().peekable();
+
+        while let Some(option) = args.next() {
+            match option.to_ascii_lowercase().as_str() {
+                "-iwad" => {
+                    let iwad = args
+                        .next_if(|arg| !arg.starts_with('-'))
+                        .ok_or_else(|| usage(format!("{} requires a file name", option)))?;
+                    plan.iwad = Some(PathBuf::from(iwad));
+                }
+                "-merge" => plan.merge.extend(Self::values(&option, &mut args)?),
+                "-file" => plan.files.extend(Self::values(&option, &mut args)?),
+                "-deh" | "-bex" => plan.dehacked.extend(Self::values(&option, &mut args)?),
+                "-warp" => {
+                    let first = args
+                        .next_if(|arg| arg.parse::<u32>().is_ok())
+                        .ok_or_else(|| usage(format!("{} requires a map number", option)))?;
+                    let second = args.next_if(|arg| arg.parse::<u32>().is_ok());
+                    plan.warp = Some(Warp {
+                        first: first.parse().unwrap(),
+                        second: second.map(|second| second.parse().unwrap()),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Takes the arguments following an option, up to the next option.
+    fn values(
+        option: &str,
+        args: &mut Peekable<impl Iterator<Item = String>>,
+    ) -> launch::Result<Vec<PathBuf>> {
+        let mut values = Vec::new();
+        while let Some(value) = args.next_if(|arg| !arg.starts_with('-')) {
+            values.push(PathBuf::from(value));
+        }
+        if values.is_empty() {
+            return Err(usage(format!("{} requires a file name", option)));
+        }
+        Ok(values)
+    }
+
+    /// Loads the resources, looking for the IWAD in the standard [search path].
+    ///
+    /// [search path]: IwadLocator::new
+    pub fn load(&self) -> launch::Result<Resources> {
+        self.load_with(&IwadLocator::new())
+    }
+
+    /// Loads the resources, looking for the IWAD with `locator`.
+    ///
+    /// The IWAD comes first, followed by the `-merge` PWADs and then the `-file` PWADs. Only the
+    /// `-merge` PWADs have their namespaces [merged]. A `-file` PWAD's namespaces replace the
+    /// earlier files', the same as in vanilla DOOM.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if no IWAD is found, if any of the files can't be loaded, or if the `-warp`
+    /// map number is too large to be a map name.
+    ///
+    /// [merged]: Wad::namespace
+    pub fn load_with(&self, locator: &IwadLocator) -> launch::Result<Resources> {
+        let iwad = match &self.iwad {
+            Some(path) if path.exists() => path.clone(),
+            Some(path) => locator.find_file(path).unwrap_or_else(|| path.clone()),
+            None => locator
+                .candidates()
+                .into_iter()
+                .next()
+                .map(|candidate| candidate.path().to_owned())
+                .ok_or_else(|| usage("no IWAD found; specify one with -iwad".to_owned()))?,
+        };
+
+        let mut wad = Wad::load(iwad)?;
+        for path in &self.merge {
+            wad = wad.patch(path)?;
+        }
+        for path in &self.files {
+            wad = wad.patch_unmerged(path)?;
+        }
+
+        let dehacked = self
+            .dehacked
+            .iter()
+            .map(|path| match fs::read(path) {
+                Ok(data) => Ok((path.clone(), Bytes::from(data))),
+                Err(err) => Err(wad::Error::Io { path: path.clone(), source: err }),
+            })
+            .collect::<wad::Result<_>>()?;
+
+        let warp = match self.warp {
+            Some(warp) => {
+                let mission = match wad.game()? {
+                    Some(game) => game.mission(),
+                    None if wad.try_lump("MAP01")?.is_some() => GameMission::Doom2,
+                    None => GameMission::Doom,
+                };
+                let map = warp.map_name(mission).ok_or_else(|| {
+                    usage(format!("-warp {} is not a valid {} map", warp, mission))
+                })?;
+                Some(map)
+            }
+            None => None,
+        };
+
+        Ok(Resources { wad, dehacked, warp })
+    }
+}
+
+impl Warp {
+    /// The marker lump of the map to warp to. Like Chocolate Doom, a lone number is a map number
+    /// in games with `MAPxx` maps and an episode number in episodic games, where it warps to the
+    /// first map of the episode.
+    ///
+    /// Returns `None` if the numbers are too large to fit in a lump name.
+    pub fn map_name(self, mission: GameMission) -> Option<LumpName> {
+        if mission.is_episodic() {
+            mission.map_name(self.first, self.second.unwrap_or(1))
+        } else {
+            mission.map_name(1, self.first)
+        }
+    }
+}
+
+impl fmt::Display for Warp {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.second {
+            Some(second) => write!(fmt, "{} {}", self.first, second),
+            None => write!(fmt, "{}", self.first),
+        }
+    }
+}
+
+fn usage(desc: String) -> LaunchError {
+    LaunchError::Usage(desc)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::wad::{Namespace, WadBuilder, WadKind};
+
+    fn parse(args: &str) -> launch::Result<ResourcePlan> {
+        ResourcePlan::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn parse_options() -> launch::Result<()> {
+        let plan =
+            parse("-skill 4 -FILE a.wad b.wad -iwad tnt.wad -deh x.deh -file c.wad -warp 1 2")?;
+        assert_eq!(plan.iwad, Some(PathBuf::from("tnt.wad")));
+        assert_eq!(plan.files, [PathBuf::from("a.wad"), "b.wad".into(), "c.wad".into()]);
+        assert_eq!(plan.dehacked, [PathBuf::from("x.deh")]);
+        assert_eq!(plan.warp.unwrap().map_name(GameMission::Doom).unwrap(), "E1M2");
+        assert_eq!(plan.warp.unwrap().map_name(GameMission::Tnt).unwrap(), "MAP01");
+
+        let warp = parse("-warp 3 -nomonsters")?.warp.unwrap();
+        assert_eq!(warp.map_name(GameMission::Doom).unwrap(), "E3M1");
+        assert_eq!(warp.map_name(GameMission::Doom2).unwrap(), "MAP03");
+
+        assert_eq!(parse("")?, ResourcePlan::default());
+        assert_matches!(parse("-file -warp 1"), Err(LaunchError::Usage(_)));
+        assert_matches!(parse("-warp E1M1"), Err(LaunchError::Usage(_)));
+
+        let warp = parse("-warp 123456 1")?.warp.unwrap();
+        assert_eq!(warp.to_string(), "123456 1");
+        assert_eq!(warp.map_name(GameMission::Doom), None);
+        assert_eq!(warp.map_name(GameMission::Doom2), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load() -> launch::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.lump("PLAYPAL", vec![0; 768]).lump("DEMO1", vec![0; 10]).marker("MAP01");
+        iwad.save(root.join("DOOM2.WAD"))?;
+        WadBuilder::new(WadKind::Pwad).lump("DEMO1", vec![1; 10]).save(root.join("a.wad"))?;
+        WadBuilder::new(WadKind::Pwad).lump("DEMO1", vec![2; 10]).save(root.join("b.wad"))?;
+        fs::write(root.join("x.deh"), "Patch File for DeHackEd v3.0").unwrap();
+
+        let mut locator = IwadLocator::empty();
+        locator.dir(root);
+
+        let file = |name: &str| root.join(name).display().to_string();
+        let args = vec![
+            "-iwad".to_owned(),
+            "doom2.wad".to_owned(),
+            "-file".to_owned(),
+            file("a.wad"),
+            "-merge".to_owned(),
+            file("b.wad"),
+            "-deh".to_owned(),
+            file("x.deh"),
+            "-warp".to_owned(),
+            "7".to_owned(),
+        ];
+        let resources = ResourcePlan::parse(args)?.load_with(&locator)?;
+
+        assert_eq!(
+            resources.wad.files().map(|file| file.path().to_owned()).collect::<Vec<_>>(),
+            [root.join("DOOM2.WAD"), root.join("b.wad"), root.join("a.wad")]
+        );
+        assert_eq!(resources.wad.lump("DEMO1")?.data(), [1; 10]);
+        assert_eq!(resources.dehacked[0].1, "Patch File for DeHackEd v3.0");
+        assert_eq!(resources.warp.unwrap(), "MAP07");
+
+        // Without -iwad the first IWAD in the search path is used.
+        let resources = parse("")?.load_with(&locator)?;
+        assert_eq!(resources.wad.files().count(), 1);
+        assert_matches!(parse("")?.load_with(&IwadLocator::empty()), Err(LaunchError::Usage(_)));
+
+        // A map number too large for a lump name is a usage error, not a panic.
+        assert_matches!(
+            parse("-warp 1000000")?.load_with(&locator),
+            Err(LaunchError::Usage(desc)) if desc == "-warp 1000000 is not a valid DOOM II map"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_replaces_flats() -> launch::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.lump("PLAYPAL", vec![0; 768]).marker("MAP01");
+        iwad.marker("F_START").lump("FLAT1", vec![0; 4096]).marker("F_END");
+        iwad.save(root.join("doom2.wad"))?;
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.marker("F_START").lump("FLAT2", vec![1; 4096]).marker("F_END");
+        pwad.save(root.join("flats.wad"))?;
+
+        let mut locator = IwadLocator::empty();
+        locator.dir(root);
+        let flats = |option: &str| -> launch::Result<Vec<String>> {
+            let args = vec![option.to_owned(), root.join("flats.wad").display().to_string()];
+            let resources = ResourcePlan::parse(args)?.load_with(&locator)?;
+            let flats = resources.wad.namespace(Namespace::Flats)?;
+            Ok(flats.iter().map(|lump| lump.name().to_string()).collect())
+        };
+
+        assert_eq!(flats("-merge")?, ["FLAT1", "FLAT2"]);
+        assert_eq!(flats("-file")?, ["FLAT2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_non_iwads() -> launch::Result<()> {
+        let stray = tempfile::tempdir().unwrap();
+        let games = tempfile::tempdir().unwrap();
+
+        fs::write(stray.path().join("doom2.wad"), "not a WAD").unwrap();
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.lump("PLAYPAL", vec![0; 768]).marker("MAP01");
+        iwad.save(games.path().join("doom2.wad"))?;
+
+        let mut locator = IwadLocator::empty();
+        locator.dir(stray.path()).dir(games.path());
+
+        // The stray file comes first in the search path, but it isn't an IWAD.
+        let resources = parse("")?.load_with(&locator)?;
+        assert_eq!(
+            resources.wad.files().map(|file| file.path().to_owned()).collect::<Vec<_>>(),
+            [games.path().join("doom2.wad")]
+        );
+
+        Ok(())
+    }
+}
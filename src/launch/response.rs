@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::launch;
+use crate::wad;
+
+/// Replaces `@file` arguments with the contents of the response file. Response files contain
+/// arguments separated by whitespace; double quotes group arguments containing spaces. Response
+/// files can include other response files.
+pub(super) fn expand(args: impl IntoIterator<Item = String>) -> launch::Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    expand_into(args, &mut expanded, &mut HashSet::new())?;
+    Ok(expanded)
+}
+
+fn expand_into(
+    args: impl IntoIterator<Item = String>,
+    expanded: &mut Vec<String>,
+    open: &mut HashSet<PathBuf>,
+) -> launch::Result<()> {
+    for arg in args {
+        let path = match arg.strip_prefix('@') {
+            Some(path) => PathBuf::from(path),
+            None => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+
+        let io_error = |err| wad::Error::Io { path: path.clone(), source: err };
+        let text = fs::read_to_string(&path).map_err(io_error)?;
+        let canonical = fs::canonicalize(&path).map_err(io_error)?;
+
+        if !open.insert(canonical.clone()) {
+            let desc = format!("{}: response file includes itself", path.display());
+            return Err(launch::LaunchError::Usage(desc));
+        }
+        expand_into(split(&text), expanded, open)?;
+        open.remove(&canonical);
+    }
+
+    Ok(())
+}
+
+/// Splits a response file into arguments.
+fn split(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+
+    for ch in text.chars() {
+        match ch {
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_with(String::new);
+            }
+            ch if ch.is_whitespace() && !quoted => args.extend(arg.take()),
+            ch => arg.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    args.extend(arg);
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            super::split("-file a.wad\n  \"my maps.wad\" \"\"\t-warp 1"),
+            ["-file", "a.wad", "my maps.wad", "", "-warp", "1"]
+        );
+    }
+
+    #[test]
+    fn expand() -> launch::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let inner = root.join("inner.txt");
+        let outer = root.join("outer.txt");
+        let looped = root.join("loop.txt");
+        fs::write(&inner, "-warp 7").unwrap();
+        fs::write(&outer, format!("-file a.wad @{}", inner.display())).unwrap();
+        fs::write(&looped, format!("@{}", looped.display())).unwrap();
+
+        let args = ["-iwad".to_owned(), "doom2.wad".to_owned(), format!("@{}", outer.display())];
+        assert_eq!(super::expand(args)?, ["-iwad", "doom2.wad", "-file", "a.wad", "-warp", "7"]);
+        assert_matches!(
+            super::expand([format!("@{}", looped.display())]),
+            Err(launch::LaunchError::Usage(_))
+        );
+        assert_matches!(
+            super::expand(["@missing.txt".to_owned()]),
+            Err(launch::LaunchError::Wad(_))
+        );
+
+        Ok(())
+    }
+}
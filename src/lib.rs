@@ -1,7 +1,12 @@
 pub mod assets;
+pub mod diff;
+pub mod launch;
 pub mod map;
 pub mod wad;
 
+// Lets derived `Record` impls name `::dusty_room` from within this crate.
+extern crate self as dusty_room;
+
 #[cfg(test)]
 #[macro_use]
 extern crate assert_matches;
@@ -3,7 +3,7 @@ use std::ops::{Deref, Index};
 use bytes::Buf;
 
 use crate::map::{Map, Sidedef, Sidedefs, Vertex, Vertexes};
-use crate::wad::{self, Lumps};
+use crate::wad::{self, Diagnostics, Lumps, MalformedKind, Record};
 
 /// A list of [linedefs] for a particular [map], indexed by number.
 ///
@@ -15,59 +15,102 @@ pub struct Linedefs(Vec<Linedef>);
 impl Linedefs {
     /// Loads a map's linedefs from its `LINEDEFS` lump.
     pub fn load(lumps: &Lumps, vertexes: &Vertexes, sidedefs: &Sidedefs) -> wad::Result<Self> {
+        Self::load_with(lumps, vertexes, sidedefs, &mut Diagnostics::strict())
+    }
+
+    /// Loads a map's linedefs from its `LINEDEFS` lump. If `diagnostics` is [lenient], invalid
+    /// vertex numbers are replaced with vertex #0, invalid left sidedefs are removed, and a partial
+    /// linedef at the end of the lump is ignored. Every linedef still needs a right sidedef.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(
+        lumps: &Lumps,
+        vertexes: &Vertexes,
+        sidedefs: &Sidedefs,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Self> {
         let lump = lumps[2].expect_name("LINEDEFS")?;
 
-        let mut linedefs = Vec::with_capacity(lump.size() / 14);
+        let mut linedefs = Vec::with_capacity(lump.size() / RawLinedef::SIZE);
         let mut cursor = lump.cursor();
 
         while cursor.has_remaining() {
+            let offset = cursor.position();
+
             // Helper function to verify a vertex number.
-            let vertex_number = |vertex: u16, which: &str| -> wad::Result<u16> {
-                vertexes.get(vertex).ok_or_else(|| {
-                    lump.error(format!(
-                        "linedef #{} has invalid {} vertex #{}",
-                        linedefs.len(),
-                        which,
-                        vertex
-                    ))
-                })?;
-                Ok(vertex)
+            let vertex_number = |vertex: u16,
+                                 which: &str,
+                                 diagnostics: &mut Diagnostics|
+             -> wad::Result<u16> {
+                if vertexes.get(vertex).is_some() {
+                    return Ok(vertex);
+                }
+
+                let err = lump.error_at(
+                    offset,
+                    MalformedKind::BadReference,
+                    format!("linedef #{} has invalid {} vertex #{}", linedefs.len(), which, vertex),
+                );
+                if vertexes.is_empty() {
+                    return Err(err);
+                }
+                diagnostics.report(err)?;
+
+                Ok(0)
             };
 
             // Helper function to verify a sidedef number. `-1` indicates no sidedef.
-            let sidedef_number = |sidedef: u16, which: &str| -> wad::Result<Option<u16>> {
+            let sidedef_number = |sidedef: u16,
+                                  which: &str,
+                                  diagnostics: &mut Diagnostics|
+             -> wad::Result<Option<u16>> {
                 if sidedef == u16::MAX {
-                    Ok(None)
-                } else {
-                    sidedefs.get(sidedef).ok_or_else(|| {
-                        lump.error(format!(
+                    return Ok(None);
+                }
+
+                if sidedefs.get(sidedef).is_none() {
+                    diagnostics.report(lump.error_at(
+                        offset,
+                        MalformedKind::BadReference,
+                        format!(
                             "linedef #{} has invalid {} sidedef #{}",
                             linedefs.len(),
                             which,
                             sidedef
-                        ))
-                    })?;
-                    Ok(Some(sidedef))
+                        ),
+                    ))?;
+                    return Ok(None);
                 }
+
+                Ok(Some(sidedef))
             };
 
-            cursor.need(14)?;
-            let start_vertex = vertex_number(cursor.get_u16_le(), "start")?;
-            let end_vertex = vertex_number(cursor.get_u16_le(), "end")?;
-            let flags = cursor.get_u16_le();
-            let types = cursor.get_u16_le();
-            let tag = cursor.get_u16_le();
-            let right_sidedef = sidedef_number(cursor.get_u16_le(), "right")?.ok_or_else(|| {
-                lump.error(format!("linedef #{} missing right sidedef", linedefs.len()))
-            })?;
-            let left_sidedef = sidedef_number(cursor.get_u16_le(), "left")?;
+            let raw: RawLinedef = match cursor.read() {
+                Ok(raw) => raw,
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    cursor.clear();
+                    break;
+                }
+            };
+            let start_vertex = vertex_number(raw.start_vertex, "start", diagnostics)?;
+            let end_vertex = vertex_number(raw.end_vertex, "end", diagnostics)?;
+            let right_sidedef = sidedef_number(raw.right_sidedef, "right", diagnostics)?
+                .ok_or_else(|| {
+                    lump.error_at(
+                        offset,
+                        MalformedKind::BadReference,
+                        format!("linedef #{} missing right sidedef", linedefs.len()),
+                    )
+                })?;
+            let left_sidedef = sidedef_number(raw.left_sidedef, "left", diagnostics)?;
 
             linedefs.push(Linedef {
                 start_vertex,
                 end_vertex,
-                flags,
-                types,
-                tag,
+                flags: raw.flags,
+                types: raw.types,
+                tag: raw.tag,
                 right_sidedef,
                 left_sidedef,
             })
@@ -163,3 +206,15 @@ impl Linedef {
         Some(&map.sidedefs[self.left_sidedef?])
     }
 }
+
+/// A linedef as stored in the `LINEDEFS` lump.
+#[derive(Record)]
+pub(crate) struct RawLinedef {
+    pub(crate) start_vertex: u16,
+    pub(crate) end_vertex: u16,
+    pub(crate) flags: u16,
+    pub(crate) types: u16,
+    pub(crate) tag: u16,
+    pub(crate) right_sidedef: u16,
+    pub(crate) left_sidedef: u16,
+}
@@ -2,13 +2,13 @@ use std::fmt;
 
 use crate::assets::Assets;
 use crate::map::{Linedefs, Sectors, Sidedefs, Vertexes};
-use crate::wad::{self, Lump, Wad};
+use crate::wad::{self, Diagnostics, Lump, LumpName, Record, ToLumpName, Wad};
 
 /// Contains all the level geometry, monsters, items, and other things that make up a map.
 #[derive(Debug)]
 pub struct Map {
     /// Map name such as `E1M1` or `MAP01`.
-    pub name: String,
+    pub name: LumpName,
 
     /// A list of things indexed by number.
     pub things: (),
@@ -32,20 +32,36 @@ impl Map {
     /// # Errors
     ///
     /// Returns `Ok(None)` if the map is missing.
-    pub fn load(wad: &Wad, name: &str, assets: &Assets) -> wad::Result<Option<Self>> {
-        let lumps = match wad.try_lumps_following(name, 11)? {
+    pub fn load(wad: &Wad, name: impl ToLumpName, assets: &Assets) -> wad::Result<Option<Self>> {
+        Self::load_with(wad, name, assets, &mut Diagnostics::strict())
+    }
+
+    /// Loads a map, recovering from malformed data if `diagnostics` is [lenient].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if the map is missing.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(
+        wad: &Wad,
+        name: impl ToLumpName,
+        assets: &Assets,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<Self>> {
+        let lumps = match wad.try_lumps_following_with(name, 11, diagnostics)? {
             Some(lumps) => lumps,
             None => return Ok(None),
         };
 
-        let name = name.to_owned();
-        let things = Self::read_things(lumps[1].expect_name("THINGS")?);
-        let vertexes = Vertexes::load(&lumps)?;
-        let sectors = Sectors::load(&lumps, assets)?;
-        let sidedefs = Sidedefs::load(&lumps, assets, &sectors)?;
-        let linedefs = Linedefs::load(&lumps, &vertexes, &sidedefs)?;
+        let name = lumps[0].name();
+        Self::read_things(lumps[1].expect_name("THINGS")?);
+        let vertexes = Vertexes::load_with(&lumps, diagnostics)?;
+        let sectors = Sectors::load_with(&lumps, assets, diagnostics)?;
+        let sidedefs = Sidedefs::load_with(&lumps, assets, &sectors, diagnostics)?;
+        let linedefs = Linedefs::load_with(&lumps, &vertexes, &sidedefs, diagnostics)?;
 
-        Ok(Some(Map { name, things, vertexes, sidedefs, linedefs, sectors }))
+        Ok(Some(Map { name, things: (), vertexes, sidedefs, linedefs, sectors }))
     }
 
     fn read_things(_lump: &Lump) {}
@@ -57,11 +73,22 @@ impl fmt::Display for Map {
     }
 }
 
+/// A thing as stored in the `THINGS` lump.
+#[derive(Record)]
+pub(crate) struct RawThing {
+    pub(crate) x: i16,
+    pub(crate) y: i16,
+    pub(crate) angle: u16,
+    pub(crate) thing_type: u16,
+    pub(crate) flags: u16,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::assets::{Flat, Texture};
     use crate::wad::test::*;
+    use crate::wad::{ErrorLocation, MalformedKind, WadBuilder, WadKind};
 
     #[test]
     fn load() {
@@ -95,4 +122,60 @@ mod tests {
             Flat { name, .. } if name == "CEIL5_1"
         );
     }
+
+    #[test]
+    fn lenient() -> wad::Result<()> {
+        let name = |name: &str| LumpName::new(name).unwrap().to_raw().to_vec();
+
+        let vertexes = [[0u8, 0, 0, 0], [64, 0, 0, 0]].concat();
+        let sector = [vec![0; 4], name("NOSUCH"), name("FLAT1"), vec![160, 0, 0, 0, 0, 0]];
+        let sidedef = [vec![0; 4], name("-"), name("MISSING"), name("-"), vec![5, 0]];
+        let linedef = [0u16, 1, 0, 0, 0, 0, 7].iter().flat_map(|n| n.to_le_bytes()).collect();
+
+        let mut builder = WadBuilder::new(WadKind::Iwad);
+        builder.lump("PLAYPAL", vec![0; 768]).lump("TEXTURE1", vec![0; 4]);
+        builder.marker("F_START").lump("FLAT1", vec![0; 4096]).marker("F_END");
+        builder.map(
+            "MAP01",
+            [
+                ("THINGS", vec![]),
+                ("LINEDEFS", linedef),
+                ("SIDEDEFS", sidedef.concat()),
+                ("VERTEXES", [vertexes, vec![0; 3]].concat()),
+                ("SEGS", vec![]),
+                ("SSECTORS", vec![]),
+                ("NODES", vec![]),
+                ("SECTORS", sector.concat()),
+                ("REJECT", vec![]),
+                ("BLOCKMAP", vec![]),
+            ],
+        );
+        builder.lump("PNAMES", vec![0; 4]).lump("COLORMAP", vec![0; 34 * 256]);
+        let wad = Wad::new(builder.build_file("lenient.wad")?)?;
+        let assets = Assets::load(&wad)?;
+
+        assert_matches!(Map::load(&wad, "MAP01", &assets), Err(_));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let map = Map::load_with(&wad, "MAP01", &assets, &mut diagnostics)?.unwrap();
+        assert_eq!(diagnostics.warnings().len(), 5);
+        assert_eq!(
+            diagnostics.warnings()[0].location(),
+            Some(&ErrorLocation { lump: "VERTEXES".parse().unwrap(), index: 9, offset: Some(8) })
+        );
+        assert_eq!(diagnostics.warnings()[0].kind(), Some(MalformedKind::Truncated));
+        assert_eq!(
+            diagnostics.warnings()[1].to_string(),
+            "lenient.wad: SECTORS at offset 0: sector #0 has invalid floor flat \"NOSUCH\""
+        );
+        assert_eq!(map.vertexes.len(), 2);
+        assert_eq!(map.sectors[0].floor_flat, "NOSUCH");
+        assert!(std::ptr::eq(map.sectors[0].floor_flat(&assets), Flat::missing()));
+        assert_eq!(map.sectors[0].ceiling_flat(&assets).name, "FLAT1");
+        assert_eq!(map.sidedefs[0].lower_texture, None);
+        assert_eq!(map.sidedefs[0].sector, 0);
+        assert_eq!(map.linedefs[0].left_sidedef, None);
+
+        Ok(())
+    }
 }
@@ -4,7 +4,7 @@ use std::ops::Index;
 use bytes::Buf;
 
 use crate::assets::{Assets, Flat};
-use crate::wad::{self, Lumps};
+use crate::wad::{self, Diagnostics, LumpName, Lumps, MalformedKind, Record};
 
 /// A list of [sectors] for a particular [map], indexed by number.
 ///
@@ -16,43 +16,66 @@ pub struct Sectors(Vec<Sector>);
 impl Sectors {
     /// Loads a map's sectors from its `SECTORS` lump.
     pub fn load(lumps: &Lumps, assets: &Assets) -> wad::Result<Self> {
+        Self::load_with(lumps, assets, &mut Diagnostics::strict())
+    }
+
+    /// Loads a map's sectors from its `SECTORS` lump. If `diagnostics` is [lenient], flats that
+    /// aren't in the [`FlatBank`] are kept and drawn with the [missing flat] placeholder, and a
+    /// partial sector at the end of the lump is ignored.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [`FlatBank`]: crate::assets::FlatBank
+    /// [missing flat]: Flat::missing
+    pub fn load_with(
+        lumps: &Lumps,
+        assets: &Assets,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Self> {
         let lump = lumps[8].expect_name("SECTORS")?;
 
-        let mut sectors = Vec::with_capacity(lump.size() / 26);
+        let mut sectors = Vec::with_capacity(lump.size() / RawSector::SIZE);
         let mut cursor = lump.cursor();
 
         while cursor.has_remaining() {
-            // Helper function to verify a flat name.
-            let flat_name = |name: String, which: &str| -> wad::Result<String> {
-                assets.flat_bank.get(&name).ok_or_else(|| {
-                    lump.error(format!(
-                        "sector #{} has invalid {} flat {:?}",
-                        sectors.len(),
-                        which,
-                        name
-                    ))
-                })?;
+            let offset = cursor.position();
 
+            // Helper function to verify a flat name.
+            let flat_name = |name: LumpName,
+                             which: &str,
+                             diagnostics: &mut Diagnostics|
+             -> wad::Result<LumpName> {
+                if assets.flat_bank.get(name).is_some() {
+                    return Ok(name);
+                }
+
+                diagnostics.report(lump.error_at(
+                    offset,
+                    MalformedKind::BadReference,
+                    format!("sector #{} has invalid {} flat {:?}", sectors.len(), which, name),
+                ))?;
                 Ok(name)
             };
 
-            cursor.need(26)?;
-            let floor_height = cursor.get_i16_le();
-            let ceiling_height = cursor.get_i16_le();
-            let floor_flat = flat_name(cursor.get_name(), "floor")?;
-            let ceiling_flat = flat_name(cursor.get_name(), "ceiling")?;
-            let light_level: u8 = cursor.get_u16_le().try_into().unwrap_or(u8::MAX);
-            let special_type = cursor.get_u16_le();
-            let tag = cursor.get_u16_le();
+            let raw: RawSector = match cursor.read() {
+                Ok(raw) => raw,
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    cursor.clear();
+                    break;
+                }
+            };
+            let floor_flat = flat_name(raw.floor_flat, "floor", diagnostics)?;
+            let ceiling_flat = flat_name(raw.ceiling_flat, "ceiling", diagnostics)?;
+            let light_level: u8 = raw.light_level.try_into().unwrap_or(u8::MAX);
 
             sectors.push(Sector {
-                floor_height,
-                ceiling_height,
+                floor_height: raw.floor_height,
+                ceiling_height: raw.ceiling_height,
                 floor_flat,
                 ceiling_flat,
                 light_level,
-                special_type,
-                tag,
+                special_type: raw.special_type,
+                tag: raw.tag,
             })
         }
 
@@ -100,18 +123,26 @@ pub struct Sector {
     /// Ceiling height.
     pub ceiling_height: i16,
 
-    /// Name of the [flat] used for the floor texture.
+    /// Name of the [flat] used for the floor texture. It can only be a flat that doesn't exist if
+    /// the map was loaded [leniently].
     ///
     /// [flat]: crate::assets::Flat
-    pub floor_flat: String,
+    /// [leniently]: Sectors::load_with
+    pub floor_flat: LumpName,
 
-    /// Name of the [flat] used for the ceiling texture.
+    /// Name of the [flat] used for the ceiling texture. It can only be a flat that doesn't exist
+    /// if the map was loaded [leniently].
     ///
     /// [flat]: crate::assets::Flat
-    pub ceiling_flat: String,
+    /// [leniently]: Sectors::load_with
+    pub ceiling_flat: LumpName,
 
     /// Light level from 0 (total dark) to 255 (maximum brightness). There are actually only 32
     /// brightnesses possible: 0-7 are the same, ..., 248-255 are the same.
+    ///
+    /// Use the [`ColormapBank`] to find the colormap for a light level at a given distance.
+    ///
+    /// [`ColormapBank`]: crate::assets::ColormapBank
     pub light_level: u8,
 
     pub special_type: u16,
@@ -124,13 +155,29 @@ pub struct Sector {
 }
 
 impl Sector {
-    /// Looks up the sector's floor flat.
+    /// Looks up the sector's floor flat, or the [missing flat] placeholder if it doesn't exist.
+    ///
+    /// [missing flat]: Flat::missing
     pub fn floor_flat<'assets>(&self, assets: &'assets Assets) -> &'assets Flat {
-        &assets.flat_bank[&self.floor_flat]
+        assets.flat_bank.get(self.floor_flat).unwrap_or(Flat::missing())
     }
 
-    /// Looks up the sector's ceiling flat.
+    /// Looks up the sector's ceiling flat, or the [missing flat] placeholder if it doesn't exist.
+    ///
+    /// [missing flat]: Flat::missing
     pub fn ceiling_flat<'assets>(&self, assets: &'assets Assets) -> &'assets Flat {
-        &assets.flat_bank[&self.ceiling_flat]
+        assets.flat_bank.get(self.ceiling_flat).unwrap_or(Flat::missing())
     }
 }
+
+/// A sector as stored in the `SECTORS` lump.
+#[derive(Record)]
+pub(crate) struct RawSector {
+    pub(crate) floor_height: i16,
+    pub(crate) ceiling_height: i16,
+    pub(crate) floor_flat: LumpName,
+    pub(crate) ceiling_flat: LumpName,
+    pub(crate) light_level: u16,
+    pub(crate) special_type: u16,
+    pub(crate) tag: u16,
+}
@@ -4,7 +4,7 @@ use bytes::Buf;
 
 use crate::assets::{Assets, Texture};
 use crate::map::{Map, Sector, Sectors};
-use crate::wad::{self, Lumps};
+use crate::wad::{self, Diagnostics, LumpName, Lumps, MalformedKind, Record};
 
 /// A list of [sidedefs] for a particular [map], indexed by number.
 ///
@@ -16,54 +16,89 @@ pub struct Sidedefs(Vec<Sidedef>);
 impl Sidedefs {
     /// Loads a map's sidedefs from its `SIDEDEFS` lump.
     pub fn load(lumps: &Lumps, assets: &Assets, sectors: &Sectors) -> wad::Result<Self> {
+        Self::load_with(lumps, assets, sectors, &mut Diagnostics::strict())
+    }
+
+    /// Loads a map's sidedefs from its `SIDEDEFS` lump. If `diagnostics` is [lenient], missing
+    /// textures are left blank, invalid sector numbers are replaced with sector #0, and a partial
+    /// sidedef at the end of the lump is ignored.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(
+        lumps: &Lumps,
+        assets: &Assets,
+        sectors: &Sectors,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Self> {
         let lump = lumps[3].expect_name("SIDEDEFS")?;
 
-        let mut sidedefs = Vec::with_capacity(lump.size() / 30);
+        let mut sidedefs = Vec::with_capacity(lump.size() / RawSidedef::SIZE);
         let mut cursor = lump.cursor();
 
         while cursor.has_remaining() {
+            let offset = cursor.position();
+
             // Helper function to verify a texture name.
-            let texture_name = |name: String, which: &str| -> wad::Result<Option<String>> {
+            let texture_name = |name: LumpName,
+                                which: &str,
+                                diagnostics: &mut Diagnostics|
+             -> wad::Result<Option<LumpName>> {
                 if name == "-" {
                     return Ok(None);
                 }
 
-                assets.texture_bank.get(&name).ok_or_else(|| {
-                    lump.error(format!(
-                        "sidedef #{} has invalid {} texture {:?}",
-                        sidedefs.len(),
-                        which,
-                        name
-                    ))
-                })?;
+                if assets.texture_bank.get(name).is_none() {
+                    diagnostics.report(lump.error_at(
+                        offset,
+                        MalformedKind::BadReference,
+                        format!(
+                            "sidedef #{} has invalid {} texture {:?}",
+                            sidedefs.len(),
+                            which,
+                            name
+                        ),
+                    ))?;
+                    return Ok(None);
+                }
 
                 Ok(Some(name))
             };
 
             // Helper function to verify a sector number.
-            let sector_number = |sector: u16| -> wad::Result<u16> {
-                sectors.get(sector).ok_or_else(|| {
-                    lump.error(format!(
-                        "sidedef #{} has invalid sector #{}",
-                        sidedefs.len(),
-                        sector
-                    ))
-                })?;
-
-                Ok(sector)
+            let sector_number = |sector: u16, diagnostics: &mut Diagnostics| -> wad::Result<u16> {
+                if sectors.get(sector).is_some() {
+                    return Ok(sector);
+                }
+
+                let err = lump.error_at(
+                    offset,
+                    MalformedKind::BadReference,
+                    format!("sidedef #{} has invalid sector #{}", sidedefs.len(), sector),
+                );
+                if sectors.is_empty() {
+                    return Err(err);
+                }
+                diagnostics.report(err)?;
+
+                Ok(0)
             };
 
-            cursor.need(30)?;
-            let x_offset = cursor.get_i16_le();
-            let y_offset = cursor.get_i16_le();
-            let upper_texture = texture_name(cursor.get_name(), "upper")?;
-            let lower_texture = texture_name(cursor.get_name(), "lower")?;
-            let middle_texture = texture_name(cursor.get_name(), "middle")?;
-            let sector = sector_number(cursor.get_u16_le())?;
+            let raw: RawSidedef = match cursor.read() {
+                Ok(raw) => raw,
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    cursor.clear();
+                    break;
+                }
+            };
+            let upper_texture = texture_name(raw.upper_texture, "upper", diagnostics)?;
+            let lower_texture = texture_name(raw.lower_texture, "lower", diagnostics)?;
+            let middle_texture = texture_name(raw.middle_texture, "middle", diagnostics)?;
+            let sector = sector_number(raw.sector, diagnostics)?;
 
             sidedefs.push(Sidedef {
-                x_offset,
-                y_offset,
+                x_offset: raw.x_offset,
+                y_offset: raw.y_offset,
                 upper_texture,
                 lower_texture,
                 middle_texture,
@@ -119,20 +154,20 @@ pub struct Sidedef {
     ///
     /// [texture]: crate::assets::Texture
     /// [sector]: crate::map::Sector
-    pub upper_texture: Option<String>,
+    pub upper_texture: Option<LumpName>,
 
     /// Optional lower [texture] name, if the adjacent [sector]'s floor is higher.
     ///
     /// [texture]: crate::assets::Texture
     /// [sector]: crate::map::Sector
-    pub lower_texture: Option<String>,
+    pub lower_texture: Option<LumpName>,
 
     /// Optional middle [texture] name. One-sided linedefs should always have a middle texture.
     /// Two-sided linedefs are usually transparent, though they sometimes have partially see-through
     /// textures such as for fences or windows.
     ///
     /// [texture]: crate::assets::Texture
-    pub middle_texture: Option<String>,
+    pub middle_texture: Option<LumpName>,
 
     /// [Sector] number this sidedef faces or helps to surround.
     ///
@@ -143,17 +178,17 @@ pub struct Sidedef {
 impl Sidedef {
     /// Looks up the sidedef's upper texture.
     pub fn upper_texture<'assets>(&self, assets: &'assets Assets) -> Option<&'assets Texture> {
-        Some(&assets.texture_bank[self.upper_texture.as_ref()?])
+        Some(&assets.texture_bank[self.upper_texture?])
     }
 
     /// Looks up the sidedef's lower texture.
     pub fn lower_texture<'assets>(&self, assets: &'assets Assets) -> Option<&'assets Texture> {
-        Some(&assets.texture_bank[self.lower_texture.as_ref()?])
+        Some(&assets.texture_bank[self.lower_texture?])
     }
 
     /// Looks up the sidedef's middle texture.
     pub fn middle_texture<'assets>(&self, assets: &'assets Assets) -> Option<&'assets Texture> {
-        Some(&assets.texture_bank[self.middle_texture.as_ref()?])
+        Some(&assets.texture_bank[self.middle_texture?])
     }
 
     /// Looks up the sidedef's sector.
@@ -161,3 +196,14 @@ impl Sidedef {
         &map.sectors[self.sector]
     }
 }
+
+/// A sidedef as stored in the `SIDEDEFS` lump.
+#[derive(Record)]
+pub(crate) struct RawSidedef {
+    pub(crate) x_offset: i16,
+    pub(crate) y_offset: i16,
+    pub(crate) upper_texture: LumpName,
+    pub(crate) lower_texture: LumpName,
+    pub(crate) middle_texture: LumpName,
+    pub(crate) sector: u16,
+}
@@ -2,7 +2,7 @@ use std::ops::{Deref, Index};
 
 use bytes::Buf;
 
-use crate::wad::{self, Lumps};
+use crate::wad::{self, Diagnostics, Lumps, Record};
 
 /// A list of [vertexes] for a particular map, indexed by number.
 ///
@@ -18,16 +18,28 @@ pub struct Vertexes(Vec<Vertex>);
 impl Vertexes {
     /// Loads a map's vertexes from its `VERTEXES` lump.
     pub fn load(lumps: &Lumps) -> wad::Result<Self> {
+        Self::load_with(lumps, &mut Diagnostics::strict())
+    }
+
+    /// Loads a map's vertexes from its `VERTEXES` lump. If `diagnostics` is [lenient], a partial
+    /// vertex at the end of the lump is ignored.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(lumps: &Lumps, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
         let lump = lumps[4].expect_name("VERTEXES")?;
 
-        let mut vertexes = Vec::with_capacity(lump.size() / 4);
+        let mut vertexes = Vec::with_capacity(lump.size() / Vertex::SIZE);
         let mut cursor = lump.cursor();
 
         while cursor.has_remaining() {
-            cursor.need(4)?;
-            let x = cursor.get_i16_le();
-            let y = cursor.get_i16_le();
-            vertexes.push(Vertex { x, y });
+            match cursor.read() {
+                Ok(vertex) => vertexes.push(vertex),
+                Err(err) => {
+                    diagnostics.report(err)?;
+                    cursor.clear();
+                    break;
+                }
+            }
         }
 
         cursor.done()?;
@@ -62,7 +74,7 @@ impl Deref for Vertexes {
 ///
 /// [linedefs]: crate::map::Linedef
 /// [segs]: crate::assets::Seg
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Record)]
 pub struct Vertex {
     /// X coordinate.
     pub x: i16,
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, io};
+
+use bytes::Bytes;
+
+use crate::wad::{self, is_legal_name, Lump, LumpName, WadFile, WadKind};
+
+/// Builds an IWAD or PWAD out of a list of lumps.
+///
+/// Lumps are written in the order they're added, with their data laid out contiguously after the
+/// 12-byte header and the lump directory at the very end, the same layout id Software used. A
+/// builder created with [`from_file`] instead keeps the original file's layout until its lumps are
+/// changed.
+///
+/// [`from_file`]: WadBuilder::from_file
+///
+/// # Examples
+///
+/// Create a PWAD containing a new flat and a one-lump map:
+///
+/// ```
+/// use dusty_room::wad::{WadBuilder, WadKind};
+///
+/// let mut builder = WadBuilder::new(WadKind::Pwad);
+/// builder
+///     .marker("FF_START")
+///     .lump("MYFLAT", vec![0; 4096])
+///     .marker("FF_END")
+///     .map("MAP01", [("THINGS", vec![0; 10])]);
+///
+/// let file = builder.build_file("my.wad")?;
+/// assert_eq!(file.lump("MYFLAT")?.size(), 4096);
+/// #
+/// # Ok::<(), dusty_room::wad::Error>(())
+/// ```
+#[derive(Clone)]
+#[must_use]
+pub struct WadBuilder {
+    kind: WadKind,
+    lumps: Vec<(LumpName, Bytes)>,
+    share_data: bool,
+    layout: Option<Layout>,
+}
+
+/// Where each piece of a WAD goes. Offsets are relative to the start of the WAD.
+#[derive(Clone, Debug)]
+struct Layout {
+    /// The offset of each lump, in directory order.
+    offsets: Vec<usize>,
+    directory_offset: usize,
+    /// Bytes that don't belong to the header, the directory, or any lump, such as padding between
+    /// lumps.
+    filler: Vec<(usize, Bytes)>,
+}
+
+impl WadBuilder {
+    /// Creates an empty IWAD or PWAD.
+    pub fn new(kind: WadKind) -> Self {
+        Self { kind, lumps: Vec::new(), share_data: false, layout: None }
+    }
+
+    /// Creates a builder pre-filled with all of the lumps from an existing file, in directory
+    /// order.
+    ///
+    /// The file's layout is kept as well: lump offsets, including those of empty lumps, the
+    /// location of the directory, and any bytes in between. Building without making any changes
+    /// reproduces the file byte-for-byte. Adding lumps or calling [`share_data`] switches back to
+    /// the standard layout.
+    ///
+    /// [`share_data`]: WadBuilder::share_data
+    pub fn from_file(file: &Arc<WadFile>) -> wad::Result<Self> {
+        let mut builder = Self::new(file.kind());
+        for lump in file.try_lumps() {
+            builder.add_lump(&lump?);
+        }
+        builder.layout = Some(
+            Self::original_layout(file)
+                .map_err(|err| wad::Error::Io { path: file.path().to_owned(), source: err })?,
+        );
+        Ok(builder)
+    }
+
+    fn original_layout(file: &WadFile) -> io::Result<Layout> {
+        let locations = file.lump_locations();
+        let directory_offset = file.directory_offset();
+
+        // Find the stretches of the file that nothing points to.
+        let mut used = vec![(0, 12), (directory_offset, directory_offset + locations.len() * 16)];
+        used.extend(
+            locations.iter().map(|location| (location.offset, location.offset + location.size)),
+        );
+        used.sort_unstable();
+
+        let mut filler = Vec::new();
+        let mut end = 0;
+        for (start, stop) in used.into_iter().chain([(file.file_size(), file.file_size())]) {
+            let start = start.min(file.file_size());
+            if start > end {
+                filler.push((end, file.read_range(end..start)?));
+            }
+            end = end.max(stop);
+        }
+
+        Ok(Layout {
+            offsets: locations.iter().map(|location| location.offset).collect(),
+            directory_offset,
+            filler,
+        })
+    }
+
+    /// Returns whether this is an IWAD or PWAD.
+    pub fn kind(&self) -> WadKind {
+        self.kind
+    }
+
+    /// The number of lumps added so far.
+    pub fn len(&self) -> usize {
+        self.lumps.len()
+    }
+
+    /// Returns `true` if no lumps have been added.
+    pub fn is_empty(&self) -> bool {
+        self.lumps.is_empty()
+    }
+
+    /// Writes lumps with identical, non-empty contents only once and points their directory
+    /// entries at the same offset. Off by default.
+    ///
+    /// The game engines don't mind shared data since lumps are read-only, but some editors do.
+    pub fn share_data(&mut self, share: bool) -> &mut Self {
+        self.share_data = share;
+        self.layout = None;
+        self
+    }
+
+    /// Appends a lump.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` isn't a [legal] lump name.
+    ///
+    /// [legal]: crate::wad::is_legal_name
+    pub fn lump(&mut self, name: &str, data: impl Into<Bytes>) -> &mut Self {
+        assert!(is_legal_name(name), "bad lump name {:?}", name);
+        let name = LumpName::new(name).unwrap();
+        self.lumps.push((name, data.into()));
+        self.layout = None;
+        self
+    }
+
+    /// Appends an empty marker lump such as `F_START` or `E1M1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` isn't a [legal] lump name.
+    ///
+    /// [legal]: crate::wad::is_legal_name
+    pub fn marker(&mut self, name: &str) -> &mut Self {
+        self.lump(name, Bytes::new())
+    }
+
+    /// Appends a copy of an existing lump. This is cheap: the lump data is shared, not copied.
+    pub fn add_lump(&mut self, lump: &Lump) -> &mut Self {
+        self.lumps.push((lump.name(), lump.bytes().clone()));
+        self.layout = None;
+        self
+    }
+
+    /// Appends a map block: a marker lump named `name` followed by the map's data lumps. The
+    /// lumps should be given in the standard order `THINGS`, `LINEDEFS`, `SIDEDEFS`, etc.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the names aren't [legal] lump names.
+    ///
+    /// [legal]: crate::wad::is_legal_name
+    pub fn map<N, D>(&mut self, name: &str, lumps: impl IntoIterator<Item = (N, D)>) -> &mut Self
+    where
+        N: AsRef<str>,
+        D: Into<Bytes>,
+    {
+        self.marker(name);
+        for (name, data) in lumps {
+            self.lump(name.as_ref(), data);
+        }
+        self
+    }
+
+    /// Serializes the WAD to a writer, starting at the writer's current position. Lump offsets are
+    /// relative to that position. When finished the writer is left positioned at the end of the
+    /// WAD.
+    ///
+    /// # Errors
+    ///
+    /// In addition to I/O errors, it is an error if the WAD would exceed the format's 4GB limit.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let standard;
+        let layout = match &self.layout {
+            Some(layout) => layout,
+            None => {
+                standard = self.standard_layout();
+                &standard
+            }
+        };
+
+        let mut directory = Vec::with_capacity(self.lumps.len() * 16);
+        for ((name, data), &offset) in self.lumps.iter().zip(&layout.offsets) {
+            directory.extend_from_slice(&Self::to_u32(offset)?.to_le_bytes());
+            directory.extend_from_slice(&Self::to_u32(data.len())?.to_le_bytes());
+            directory.extend_from_slice(&name.to_raw());
+        }
+
+        let magic = match self.kind {
+            WadKind::Iwad => b"IWAD",
+            WadKind::Pwad => b"PWAD",
+        };
+        writer.write_all(magic)?;
+        writer.write_all(&Self::to_u32(self.lumps.len())?.to_le_bytes())?;
+        writer.write_all(&Self::to_u32(layout.directory_offset)?.to_le_bytes())?;
+
+        // Write everything else in file order. Pieces that overlap what's already been written,
+        // such as shared data, are skipped.
+        let mut pieces = self
+            .lumps
+            .iter()
+            .zip(&layout.offsets)
+            .map(|((_, data), &offset)| (offset, &data[..]))
+            .chain([(layout.directory_offset, &directory[..])])
+            .chain(layout.filler.iter().map(|(offset, data)| (*offset, &data[..])))
+            .filter(|(_, data)| !data.is_empty())
+            .collect::<Vec<_>>();
+        pieces.sort_by_key(|&(offset, _)| offset);
+
+        let mut position: usize = 12;
+        for (offset, data) in pieces {
+            if offset > position {
+                writer.write_all(&vec![0; offset - position])?;
+                position = offset;
+            }
+            let skip = position - offset;
+            if skip < data.len() {
+                writer.write_all(&data[skip..])?;
+                position = offset + data.len();
+            }
+        }
+        Ok(())
+    }
+
+    /// Lays out lumps contiguously in the order they were added, followed by the directory.
+    fn standard_layout(&self) -> Layout {
+        let mut offset: usize = 12;
+        let mut offsets = Vec::with_capacity(self.lumps.len());
+        // Offsets of data already laid out, if sharing is enabled.
+        let mut written: HashMap<&[u8], usize> = HashMap::new();
+
+        for (_, data) in &self.lumps {
+            match written.get(&data[..]) {
+                Some(&shared_offset) => offsets.push(shared_offset),
+                None => {
+                    offsets.push(offset);
+                    if self.share_data && !data.is_empty() {
+                        written.insert(data, offset);
+                    }
+                    offset += data.len();
+                }
+            }
+        }
+
+        Layout { offsets, directory_offset: offset, filler: Vec::new() }
+    }
+
+    fn to_u32(value: usize) -> io::Result<u32> {
+        u32::try_from(value)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "WAD too large"))
+    }
+
+    /// Serializes the WAD to an in-memory buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the WAD would exceed the format's 4GB limit.
+    pub fn build(&self) -> Bytes {
+        let size = 12 + self.lumps.iter().map(|(_, data)| data.len() + 16).sum::<usize>();
+        let mut raw = io::Cursor::new(Vec::with_capacity(size));
+        self.write(&mut raw).expect("WAD too large");
+        Bytes::from(raw.into_inner())
+    }
+
+    /// Serializes the WAD and loads it as a [`WadFile`] without touching the disk.
+    ///
+    /// The `path` only used for display purposes, such as in error messages. It doesn't need to
+    /// point to an actual file on disk.
+    pub fn build_file(&self, path: impl AsRef<Path>) -> wad::Result<Arc<WadFile>> {
+        WadFile::load_raw(path, self.build())
+    }
+
+    /// Saves the WAD to disk, overwriting the file if it already exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> wad::Result<()> {
+        let path = path.as_ref();
+        let io_error = |err| wad::Error::Io { path: path.to_owned(), source: err };
+
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        self.write(&mut writer).map_err(io_error)?;
+        writer.flush().map_err(io_error)
+    }
+}
+
+impl fmt::Debug for WadBuilder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let Self { kind, lumps, share_data, layout: _ } = self;
+
+        fmt.debug_struct("WadBuilder")
+            .field("kind", &kind)
+            .field("share_data", &share_data)
+            .field(
+                "lumps",
+                &lumps
+                    .iter()
+                    .map(|(name, data)| format!("{} ({} bytes)", name, data.len()))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, SeekFrom};
+
+    use super::*;
+    use crate::wad::test::*;
+
+    #[test]
+    fn build() {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.marker("F_START").lump("FLAT1", vec![1; 4096]).marker("F_END");
+        builder.map("E1M1", [("THINGS", vec![2; 10]), ("LINEDEFS", vec![3; 14])]);
+
+        let file = builder.build_file("test.wad").unwrap();
+        assert_eq!(file.kind(), WadKind::Pwad);
+        assert_eq!(
+            file.lumps().map(|lump| (lump.name().to_string(), lump.size())).collect::<Vec<_>>(),
+            [
+                ("F_START".to_owned(), 0),
+                ("FLAT1".to_owned(), 4096),
+                ("F_END".to_owned(), 0),
+                ("E1M1".to_owned(), 0),
+                ("THINGS".to_owned(), 10),
+                ("LINEDEFS".to_owned(), 14),
+            ],
+        );
+        assert_eq!(file.lump("THINGS").unwrap().data(), [2; 10]);
+        assert_eq!(file.lumps_following("E1M1", 3).unwrap().len(), 3);
+
+        // Rebuilding the loaded file reproduces it byte-for-byte.
+        assert_eq!(WadBuilder::from_file(&file).unwrap().build(), builder.build());
+    }
+
+    #[test]
+    fn share_data() {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("DEMO1", vec![1; 10]).lump("DEMO2", vec![1; 10]).lump("DEMO3", vec![2; 10]);
+        assert_eq!(builder.build().len(), 12 + 30 + 3 * 16);
+
+        builder.share_data(true);
+        let raw = builder.build();
+        assert_eq!(raw.len(), 12 + 20 + 3 * 16);
+
+        let file = WadFile::load_raw("test.wad", raw).unwrap();
+        assert_eq!(file.lump("DEMO2").unwrap().data(), [1; 10]);
+        assert_eq!(file.lump("DEMO3").unwrap().data(), [2; 10]);
+    }
+
+    #[test]
+    fn write_at_offset() {
+        let mut builder = WadBuilder::new(WadKind::Iwad);
+        builder.lump("PLAYPAL", vec![0; 768]);
+
+        // Write into the middle of a buffer, in front of data that should be overwritten.
+        let mut raw = io::Cursor::new(vec![b'x'; 1000]);
+        raw.get_mut()[..4].copy_from_slice(b"junk");
+        raw.seek(SeekFrom::Start(4)).unwrap();
+        builder.write(&mut raw).unwrap();
+
+        // The writer is left at the end of the WAD, not the end of the buffer.
+        let end = 4 + 12 + 768 + 16;
+        assert_eq!(raw.position(), end as u64);
+        raw.write_all(b"tail").unwrap();
+
+        let raw = raw.into_inner();
+        assert_eq!(raw.len(), 1000);
+        assert_eq!(&raw[..8], b"junkIWAD");
+        assert_eq!(raw[4..end], builder.build());
+        assert_eq!(&raw[end..end + 4], b"tail");
+        assert!(raw[end + 4..].iter().all(|&b| b == b'x'));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_name() {
+        WadBuilder::new(WadKind::Pwad).marker("lower");
+    }
+
+    #[test]
+    fn round_trip() {
+        for path in [DOOM_WAD_PATH, DOOM2_WAD_PATH, KILLER_WAD_PATH, BIOTECH_WAD_PATH] {
+            let original = WadFile::load(path).unwrap();
+            let raw = WadBuilder::from_file(&original).unwrap().build();
+            let copy = WadFile::load_raw(path, raw.clone()).unwrap();
+
+            assert_eq!(copy.kind(), original.kind());
+            assert!(original
+                .lumps()
+                .zip(copy.lumps())
+                .all(|(a, b)| a.name() == b.name() && a.data() == b.data()));
+            assert_eq!(original.lumps().count(), copy.lumps().count());
+            assert!(raw == std::fs::read(path).unwrap());
+        }
+    }
+
+    #[test]
+    fn keep_layout() {
+        let entry = |offset: u32, size: u32, name| {
+            [&offset.to_le_bytes()[..], &size.to_le_bytes(), &LumpName::new(name).unwrap().to_raw()]
+                .concat()
+        };
+
+        // Directory first, then padding, then two lumps sharing data. The marker points into the
+        // middle of the trailer.
+        let raw = Bytes::from(
+            [
+                &b"PWAD"[..],
+                &3u32.to_le_bytes(),
+                &12u32.to_le_bytes(),
+                &entry(64, 4, "DEMO1"),
+                &entry(70, 0, "M_START"),
+                &entry(64, 4, "DEMO2"),
+                b"pad!",
+                b"demo",
+                b"trailer",
+            ]
+            .concat(),
+        );
+
+        let file = WadFile::load_raw("test.wad", raw.clone()).unwrap();
+        let mut builder = WadBuilder::from_file(&file).unwrap();
+        assert_eq!(builder.build(), raw);
+
+        // Changing the lumps switches to the standard layout.
+        builder.marker("M_END");
+        let mut standard = WadBuilder::new(WadKind::Pwad);
+        standard.lump("DEMO1", &b"demo"[..]).marker("M_START").lump("DEMO2", &b"demo"[..]);
+        standard.marker("M_END");
+        assert_eq!(builder.build(), standard.build());
+    }
+}
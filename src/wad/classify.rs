@@ -0,0 +1,418 @@
+use std::fmt;
+
+use bytes::Buf;
+
+use crate::wad::resolved::MAP_LUMPS;
+use crate::wad::{Lump, LumpName, Namespace};
+
+/// The kinds of data a lump can hold, as guessed by [`Lump::classify`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LumpFormat {
+    /// An empty lump such as `F_START`, used only for its name.
+    Marker,
+
+    /// The marker at the start of a map, like `E1M1` or `MAP01`.
+    MapHeader,
+
+    /// One of a map's data lumps, like `THINGS` or `BLOCKMAP`.
+    MapData,
+
+    /// A 64x64 floor or ceiling texture.
+    Flat,
+
+    /// A picture in the column-based patch format used by wall patches, sprites, and menu
+    /// graphics.
+    Patch,
+
+    /// A PNG image.
+    Png,
+
+    /// Music in id's MUS format.
+    Mus,
+
+    /// A standard MIDI file.
+    Midi,
+
+    /// A digitized sound in the DMX format.
+    DmxSound,
+
+    /// A PC speaker sound effect.
+    PcSpeakerSound,
+
+    /// The `PLAYPAL` color palettes.
+    Playpal,
+
+    /// The `COLORMAP` light level tables, or one of Boom's extra colormaps.
+    Colormap,
+
+    /// A `TEXTURE1` or `TEXTURE2` texture list.
+    Textures,
+
+    /// The `PNAMES` list of patch names.
+    Pnames,
+
+    /// A DeHackEd or BEX patch.
+    Dehacked,
+
+    /// Plain text, such as `MAPINFO` or a lump of level credits.
+    Text,
+}
+
+/// How sure [`Lump::classify`] is of a guess.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The data fits, but so would lots of other data.
+    Low,
+
+    /// The data fits and the name or namespace agrees.
+    Medium,
+
+    /// The data passes a structural check that random data would fail.
+    High,
+
+    /// The data has a magic number, or its name and structure both match.
+    Certain,
+}
+
+/// A guess at a lump's format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Classification {
+    /// The format.
+    pub format: LumpFormat,
+
+    /// How likely it is.
+    pub confidence: Confidence,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:?} ({:?})", self.format, self.confidence)
+    }
+}
+
+impl Lump {
+    /// Guesses what the lump contains from its name, [namespace], and contents. Every plausible
+    /// format is returned, most likely first. The list is empty if nothing fits.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{LumpFormat, Wad};
+    ///
+    /// let wad = Wad::load("doom.wad")?;
+    /// let lump = wad.lump("D_E1M1")?;
+    /// assert_eq!(lump.format(), Some(LumpFormat::Mus));
+    ///
+    /// for guess in wad.lump("TITLEPIC")?.classify() {
+    ///     println!("{}", guess);
+    /// }
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [namespace]: Self::namespace
+    pub fn classify(&self) -> Vec<Classification> {
+        let mut guesses = Vec::new();
+
+        let name = self.name();
+        let namespace = self.namespace();
+        let data = self.data();
+
+        let next = self.file().lump_locations().get(self.index() + 1).map(|next| next.name);
+        if namespace == Namespace::Global && next.is_some_and(|next| next == "THINGS") {
+            guesses.push((LumpFormat::MapHeader, Confidence::Certain));
+        } else if data.is_empty() && is_map_name(name.as_bytes()) {
+            guesses.push((LumpFormat::MapHeader, Confidence::Medium));
+        }
+
+        if !data.is_empty() {
+            guess_contents(name, namespace, data, &mut guesses);
+        } else if guesses.is_empty() {
+            guesses.push((LumpFormat::Marker, Confidence::Certain));
+        }
+
+        // The sort is stable so ties stay in the order they were checked.
+        guesses.sort_by(|(_, a), (_, b)| b.cmp(a));
+        guesses
+            .into_iter()
+            .map(|(format, confidence)| Classification { format, confidence })
+            .collect()
+    }
+
+    /// The most likely format from [`classify`], if any.
+    ///
+    /// [`classify`]: Self::classify
+    pub fn format(&self) -> Option<LumpFormat> {
+        self.classify().first().map(|guess| guess.format)
+    }
+}
+
+/// Guesses the format of a non-empty lump from its contents.
+fn guess_contents(
+    name: LumpName,
+    namespace: Namespace,
+    data: &[u8],
+    guesses: &mut Vec<(LumpFormat, Confidence)>,
+) {
+    if MAP_LUMPS.iter().any(|&map_lump| name == map_lump) {
+        guesses.push((LumpFormat::MapData, Confidence::High));
+    }
+
+    // Magic numbers.
+    if data.starts_with(b"MUS\x1a") {
+        guesses.push((LumpFormat::Mus, Confidence::Certain));
+    }
+    if data.starts_with(b"MThd") {
+        guesses.push((LumpFormat::Midi, Confidence::Certain));
+    }
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        guesses.push((LumpFormat::Png, Confidence::Certain));
+    }
+    if data.starts_with(b"Patch File for DeHackEd") {
+        guesses.push((LumpFormat::Dehacked, Confidence::Certain));
+    } else if name == "DEHACKED" && is_text(data) {
+        guesses.push((LumpFormat::Dehacked, Confidence::High));
+    }
+
+    // Fixed-size tables.
+    if data.len().is_multiple_of(768) {
+        match (name == "PLAYPAL", data.len() == 14 * 768) {
+            (true, _) => guesses.push((LumpFormat::Playpal, Confidence::Certain)),
+            (false, true) => guesses.push((LumpFormat::Playpal, Confidence::Medium)),
+            (false, false) => guesses.push((LumpFormat::Playpal, Confidence::Low)),
+        }
+    }
+    if data.len().is_multiple_of(256) {
+        if name == "COLORMAP" {
+            guesses.push((LumpFormat::Colormap, Confidence::Certain));
+        } else if namespace == Namespace::Colormaps {
+            guesses.push((LumpFormat::Colormap, Confidence::High));
+        } else if data.len() == 34 * 256 {
+            guesses.push((LumpFormat::Colormap, Confidence::Medium));
+        }
+    }
+    if namespace == Namespace::Flats {
+        let confidence = if data.len() == 64 * 64 { Confidence::Certain } else { Confidence::High };
+        guesses.push((LumpFormat::Flat, confidence));
+    } else if data.len() == 64 * 64 {
+        guesses.push((LumpFormat::Flat, Confidence::Low));
+    }
+
+    // Structured data.
+    if is_pnames(data) {
+        let confidence = if name == "PNAMES" { Confidence::Certain } else { Confidence::Low };
+        guesses.push((LumpFormat::Pnames, confidence));
+    }
+    if is_texture_list(data) {
+        let named = name.as_bytes().starts_with(b"TEXTURE");
+        guesses.push((
+            LumpFormat::Textures,
+            if named { Confidence::Certain } else { Confidence::Low },
+        ));
+    }
+    if is_patch(data) {
+        let confidence = match namespace {
+            Namespace::Sprites | Namespace::Patches => Confidence::Certain,
+            _ => Confidence::High,
+        };
+        guesses.push((LumpFormat::Patch, confidence));
+    }
+    if let Some(exact) = is_dmx_sound(data) {
+        let confidence = if exact { Confidence::High } else { Confidence::Medium };
+        guesses.push((LumpFormat::DmxSound, confidence));
+    }
+    if is_pc_speaker_sound(data) {
+        guesses.push((LumpFormat::PcSpeakerSound, Confidence::High));
+    }
+
+    if is_text(data) && !guesses.iter().any(|&(format, _)| format == LumpFormat::Dehacked) {
+        guesses.push((LumpFormat::Text, Confidence::Medium));
+    }
+}
+
+/// Checks for `ExMy` and `MAPxx` names.
+fn is_map_name(name: &[u8]) -> bool {
+    match name {
+        [b'E', episode, b'M', map] => episode.is_ascii_digit() && map.is_ascii_digit(),
+        [b'M', b'A', b'P', tens, ones] => tens.is_ascii_digit() && ones.is_ascii_digit(),
+        _ => false,
+    }
+}
+
+/// Checks for printable ASCII. Control characters other than whitespace are rejected, except for
+/// the DOS end-of-file marker.
+fn is_text(data: &[u8]) -> bool {
+    data.iter().all(|&byte| {
+        byte.is_ascii_graphic() || byte.is_ascii_whitespace() || byte == b'\x1a' || byte >= 0x80
+    }) && data.iter().any(u8::is_ascii_alphanumeric)
+}
+
+/// Checks for a count followed by that many 8-byte names.
+fn is_pnames(mut data: &[u8]) -> bool {
+    if data.len() < 4 {
+        return false;
+    }
+    let count = data.get_u32_le() as usize;
+    count.checked_mul(8) == Some(data.len())
+}
+
+/// Checks for a count followed by that many offsets, each pointing at a texture whose patch
+/// list fits in the lump.
+fn is_texture_list(data: &[u8]) -> bool {
+    let mut header = data;
+    if header.len() < 4 {
+        return false;
+    }
+    let count = header.get_u32_le() as usize;
+    if count == 0 || count.checked_mul(4).is_none_or(|size| size > header.len()) {
+        return false;
+    }
+
+    (0..count).all(|_| {
+        let offset = header.get_u32_le() as usize;
+        let mut texture = match data.get(offset..) {
+            Some(texture) if texture.len() >= 22 => texture,
+            _ => return false,
+        };
+        texture.advance(20);
+        let patch_count = usize::from(texture.get_u16_le());
+        texture.len() >= patch_count * 10
+    })
+}
+
+/// Checks the patch header and that every column is a well-formed list of posts inside the lump.
+fn is_patch(data: &[u8]) -> bool {
+    let mut header = data;
+    if header.len() < 8 {
+        return false;
+    }
+    let width = usize::from(header.get_u16_le());
+    let height = usize::from(header.get_u16_le());
+    header.advance(4);
+
+    let columns_start = 8 + 4 * width;
+    if width == 0 || height == 0 || width > 4096 || height > 4096 || data.len() < columns_start {
+        return false;
+    }
+
+    (0..width).all(|_| {
+        let offset = header.get_u32_le() as usize;
+        offset >= columns_start && is_column(data.get(offset..).unwrap_or_default())
+    })
+}
+
+/// Checks for a series of posts ending with `0xFF`.
+fn is_column(mut column: &[u8]) -> bool {
+    loop {
+        match column.first() {
+            None => return false,
+            Some(0xFF) => return true,
+            Some(_) => {}
+        }
+        if column.len() < 2 {
+            return false;
+        }
+        let length = usize::from(column[1]);
+        if column.len() < length + 4 {
+            return false;
+        }
+        column.advance(length + 4);
+    }
+}
+
+/// Checks for a DMX sound header. Returns `Some(true)` if the sample count covers exactly the
+/// rest of the lump and `Some(false)` if it's shorter.
+fn is_dmx_sound(mut data: &[u8]) -> Option<bool> {
+    if data.len() < 8 {
+        return None;
+    }
+    let format = data.get_u16_le();
+    let rate = data.get_u16_le();
+    let samples = data.get_u32_le() as usize;
+
+    if format != 3 || rate == 0 || samples > data.len() {
+        return None;
+    }
+    Some(samples == data.len())
+}
+
+/// Checks for a PC speaker sound header: a zero followed by the number of tones.
+fn is_pc_speaker_sound(mut data: &[u8]) -> bool {
+    if data.len() < 4 {
+        return false;
+    }
+    let format = data.get_u16_le();
+    let count = usize::from(data.get_u16_le());
+    format == 0 && count > 0 && count == data.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{self, WadBuilder, WadKind};
+
+    fn formats(lump: &Lump) -> Vec<(LumpFormat, Confidence)> {
+        lump.classify().into_iter().map(|guess| (guess.format, guess.confidence)).collect()
+    }
+
+    #[test]
+    fn classify() -> wad::Result<()> {
+        // A 2x2 patch with one post per column.
+        let mut patch = vec![2, 0, 2, 0, 0, 0, 0, 0, 16, 0, 0, 0, 23, 0, 0, 0];
+        patch.extend([0, 2, 0, 1, 1, 0, 0xFF]);
+        patch.extend([0, 1, 0, 1, 0, 0xFF]);
+
+        let mut dmx = vec![3, 0, 0x11, 0x2B, 4, 0, 0, 0];
+        dmx.extend([0x80; 4]);
+
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder
+            .lump("PLAYPAL", vec![0; 768 * 14])
+            .lump("COLORMAP", vec![0; 256 * 34])
+            .lump("PNAMES", b"\x01\0\0\0WALL00_1".to_vec())
+            .lump("D_RUNNIN", b"MUS\x1a\0\0".to_vec())
+            .lump("DSPISTOL", dmx)
+            .lump("DPPISTOL", vec![0, 0, 2, 0, 30, 40])
+            .lump("DEHACKED", b"Patch File for DeHackEd v3.0\n".to_vec())
+            .lump("CREDITS", b"Thanks for playing!\r\n".to_vec())
+            .lump("PNGPIC", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec())
+            .lump("WALL00_1", patch.clone())
+            .marker("F_START")
+            .lump("FLOOR0_1", vec![0; 4096])
+            .marker("F_END")
+            .marker("S_START")
+            .lump("TROOA1", patch)
+            .marker("S_END")
+            .map("E1M1", [("THINGS", vec![0; 10])]);
+        let file = builder.build_file("classify.wad")?;
+
+        let lump = |name| file.lump(name);
+        assert_eq!(lump("PLAYPAL")?.format(), Some(LumpFormat::Playpal));
+        assert_eq!(lump("COLORMAP")?.format(), Some(LumpFormat::Colormap));
+        assert_eq!(formats(&lump("PNAMES")?), [(LumpFormat::Pnames, Confidence::Certain)]);
+        assert_eq!(formats(&lump("D_RUNNIN")?), [(LumpFormat::Mus, Confidence::Certain)]);
+        assert_eq!(formats(&lump("DSPISTOL")?), [(LumpFormat::DmxSound, Confidence::High)]);
+        assert_eq!(lump("DPPISTOL")?.format(), Some(LumpFormat::PcSpeakerSound));
+        assert_eq!(formats(&lump("DEHACKED")?), [(LumpFormat::Dehacked, Confidence::Certain)]);
+        assert_eq!(formats(&lump("CREDITS")?), [(LumpFormat::Text, Confidence::Medium)]);
+        assert_eq!(lump("PNGPIC")?.format(), Some(LumpFormat::Png));
+        assert_eq!(formats(&lump("WALL00_1")?), [(LumpFormat::Patch, Confidence::High)]);
+        assert_eq!(formats(&lump("TROOA1")?), [(LumpFormat::Patch, Confidence::Certain)]);
+        assert_eq!(lump("FLOOR0_1")?.format(), Some(LumpFormat::Flat));
+        assert_eq!(formats(&lump("F_START")?), [(LumpFormat::Marker, Confidence::Certain)]);
+        assert_eq!(formats(&lump("E1M1")?), [(LumpFormat::MapHeader, Confidence::Certain)]);
+        assert_eq!(lump("THINGS")?.format(), Some(LumpFormat::MapData));
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_patch() {
+        // Column offsets that point into the header or past the end aren't patches.
+        assert!(!is_patch(&[1, 0, 1, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0xFF]));
+        assert!(!is_patch(&[1, 0, 1, 0, 0, 0, 0, 0, 12, 1, 0, 0, 0xFF]));
+        assert!(is_patch(&[1, 0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0xFF]));
+        // A post that runs off the end.
+        assert!(!is_patch(&[1, 0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0, 9, 0, 0, 0xFF]));
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::wad::{self, Diagnostics, LintKind, Lump, MalformedKind, WadBuilder, WadFile};
+
+/// Options for [`WadFile::compact`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CompactOptions {
+    /// Drop [empty lumps] and end markers that have no matching start marker. Namespace markers
+    /// and map markers are always kept.
+    ///
+    /// [empty lumps]: crate::wad::LintKind::EmptyLump
+    pub remove_empty: bool,
+}
+
+/// A rebuilt WAD produced by [`WadFile::compact`].
+#[derive(Clone, Debug)]
+pub struct Compaction {
+    raw: Bytes,
+    original_size: usize,
+    removed: Vec<Lump>,
+}
+
+impl Compaction {
+    /// The rebuilt WAD.
+    pub fn bytes(&self) -> &Bytes {
+        &self.raw
+    }
+
+    /// Loads the rebuilt WAD as a [`WadFile`] without touching the disk. `path` is only used for
+    /// display purposes.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> wad::Result<Arc<WadFile>> {
+        WadFile::load_raw(path, self.raw.clone())
+    }
+
+    /// Saves the rebuilt WAD to disk, overwriting the file if it already exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> wad::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, &self.raw)
+            .map_err(|err| wad::Error::Io { path: path.to_owned(), source: err })
+    }
+
+    /// The size of the original file in bytes.
+    pub fn original_size(&self) -> usize {
+        self.original_size
+    }
+
+    /// The size of the rebuilt WAD in bytes.
+    pub fn compacted_size(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// How many bytes smaller the rebuilt WAD is. Zero if it didn't shrink.
+    pub fn bytes_saved(&self) -> usize {
+        self.original_size.saturating_sub(self.raw.len())
+    }
+
+    /// The lumps that were dropped, in directory order.
+    pub fn removed(&self) -> &[Lump] {
+        &self.removed
+    }
+}
+
+pub(super) fn compact(file: &Arc<WadFile>, options: CompactOptions) -> wad::Result<Compaction> {
+    let mut removals = HashSet::new();
+
+    if options.remove_empty {
+        let report = file.lint();
+        removals.extend(
+            report
+                .findings()
+                .iter()
+                .filter(|finding| finding.kind == LintKind::EmptyLump)
+                .filter_map(|finding| finding.location.as_ref())
+                .map(|location| location.index),
+        );
+
+        let mut diagnostics = Diagnostics::lenient();
+        file.namespaces_with(&mut diagnostics)?;
+        removals.extend(
+            diagnostics
+                .into_warnings()
+                .iter()
+                .filter(|warning| warning.kind() == Some(MalformedKind::Missing))
+                .filter_map(|warning| warning.location())
+                .filter(|location| file.lump_locations()[location.index].size == 0)
+                .map(|location| location.index),
+        );
+    }
+
+    let mut builder = WadBuilder::new(file.kind());
+    builder.share_data(true);
+    let mut removed = Vec::new();
+
+    for (index, lump) in file.try_lumps().enumerate() {
+        let lump = lump?;
+        if removals.contains(&index) {
+            removed.push(lump);
+        } else {
+            builder.add_lump(&lump);
+        }
+    }
+
+    Ok(Compaction { raw: builder.build(), original_size: file.file_size(), removed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::WadKind;
+
+    /// A WAD with a duplicated lump body, an empty lump, an orphan end marker, and junk between
+    /// lumps.
+    fn bloated() -> Bytes {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("DEMO1", vec![1; 100]).lump("DEMO2", vec![1; 100]).marker("EMPTY");
+        builder.marker("S_END");
+        builder.map("MAP01", [("THINGS", vec![2; 10])]);
+
+        let mut raw = builder.build().to_vec();
+        // Shift the directory back to leave 50 bytes of junk in front of it.
+        let directory_offset = u32::from_le_bytes(raw[8..12].try_into().unwrap());
+        raw.splice(directory_offset as usize..directory_offset as usize, [0xff; 50]);
+        raw[8..12].copy_from_slice(&(directory_offset + 50).to_le_bytes());
+        Bytes::from(raw)
+    }
+
+    fn contents(file: &Arc<WadFile>) -> Vec<(String, Bytes)> {
+        file.lumps().map(|lump| (lump.name().to_string(), lump.bytes().clone())).collect()
+    }
+
+    #[test]
+    fn compact() -> wad::Result<()> {
+        let file = WadFile::load_raw("bloated.wad", bloated())?;
+
+        let compaction = file.compact(CompactOptions::default())?;
+        assert_eq!(compaction.original_size(), 12 + 210 + 50 + 6 * 16);
+        assert_eq!(compaction.bytes_saved(), 150);
+        assert!(compaction.removed().is_empty());
+
+        let compacted = compaction.to_file("compacted.wad")?;
+        assert_eq!(contents(&compacted), contents(&file));
+        assert_eq!(compacted.lint().wasted_bytes(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_empty() -> wad::Result<()> {
+        let file = WadFile::load_raw("bloated.wad", bloated())?;
+
+        let compaction = file.compact(CompactOptions { remove_empty: true })?;
+        assert_eq!(compaction.bytes_saved(), 150 + 2 * 16);
+        assert_eq!(
+            compaction.removed().iter().map(|lump| lump.name().to_string()).collect::<Vec<_>>(),
+            ["EMPTY", "S_END"],
+        );
+
+        let compacted = compaction.to_file("compacted.wad")?;
+        assert!(compacted.lint().is_clean(), "{}", compacted.lint());
+        assert_eq!(
+            contents(&compacted),
+            contents(&file)
+                .into_iter()
+                .filter(|(name, _)| name != "EMPTY" && name != "S_END")
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(())
+    }
+}
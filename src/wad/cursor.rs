@@ -1,12 +1,17 @@
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::ops::{Deref, DerefMut};
 
 use bytes::{Buf, Bytes};
 
-use crate::wad::{self, parse_name, Lump};
+use crate::wad::{self, Lump, LumpName, MalformedKind, Record};
 
 /// A moving cursor for reading data from a [`Lump`]. `Cursor` is a thin wrapper around [`Bytes`]
-/// that allows for checking if there's data available before reading it.
+/// that allows for checking if there's data available before reading it. It keeps track of its
+/// offset within the lump so errors can point at the data that caused them.
+///
+/// Formats with offset tables can jump around with [`seek`] or spawn sub-cursors with [`at`] and
+/// [`split`]. Sub-cursors can't read past the end of the cursor they came from.
 ///
 /// It is important to always call [`done`] when when parsing is finished to ensure there is no
 /// extra trailing data. You can [`clear`] the cursor if trailing data is expected.
@@ -46,25 +51,100 @@ use crate::wad::{self, parse_name, Lump};
 ///
 /// [`done`]: Self::done
 /// [`clear`]: Bytes::clear
+/// [`seek`]: Self::seek
+/// [`at`]: Self::at
+/// [`split`]: Self::split
 pub struct Cursor<'lump> {
     lump: &'lump Lump,
     data: Bytes,
+    start: usize,
+    end: usize,
 }
 
 impl<'lump> Cursor<'lump> {
     pub(super) fn new(lump: &'lump Lump, data: Bytes) -> Self {
-        Self { lump, data }
+        let end = data.len();
+        Self { lump, data, start: 0, end }
+    }
+
+    /// Creates a sub-cursor starting at `offset` within the lump. It ends where this cursor ends.
+    /// This cursor isn't moved.
+    ///
+    /// # Examples
+    ///
+    /// Read a table of 4-byte offsets and the 2-byte values they point to:
+    ///
+    /// ```no_run
+    /// # let lump = dusty_room::wad::Wad::load("")?.lump("")?;
+    /// #
+    /// let mut cursor = lump.cursor();
+    ///
+    /// let count: u32 = cursor.read()?;
+    /// for offset in cursor.read_array::<u32>(count as usize)? {
+    ///     let mut entry = cursor.at(offset as usize)?;
+    ///     let value: u16 = entry.read()?;
+    /// }
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// It is an error if `offset` is outside of this cursor's bounds.
+    pub fn at(&self, offset: usize) -> wad::Result<Cursor<'lump>> {
+        let mut cursor =
+            Cursor { lump: self.lump, data: self.data.clone(), start: self.start, end: self.end };
+        cursor.seek(offset)?;
+        Ok(cursor)
+    }
+
+    /// Splits off the next `size` bytes as a sub-cursor and moves this cursor past them.
+    pub fn split(&mut self, size: usize) -> wad::Result<Cursor<'lump>> {
+        self.need(size)?;
+        let start = self.position();
+        let data = self.split_to(size);
+        Ok(Cursor { lump: self.lump, data, start, end: start + size })
     }
 }
 
 impl Cursor<'_> {
+    /// The offset of the next byte within the lump.
+    pub fn position(&self) -> usize {
+        self.end - self.len()
+    }
+
+    /// Moves to `offset` within the lump. Cursors can move backwards as well as forwards, but not
+    /// outside of their bounds.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if `offset` is outside of this cursor's bounds.
+    pub fn seek(&mut self, offset: usize) -> wad::Result<()> {
+        if offset < self.start || offset > self.end {
+            return Err(self.lump.error_at(
+                offset,
+                MalformedKind::OutOfBounds,
+                format!("offset {} outside of {}..{}", offset, self.start, self.end),
+            ));
+        }
+
+        self.data = self.lump.bytes().slice(offset..self.end);
+        Ok(())
+    }
+
+    /// Creates a [`wad::Error::Malformed`] blaming the data at the current position.
+    pub fn error(&self, kind: MalformedKind, desc: impl Into<Cow<'static, str>>) -> wad::Error {
+        self.lump.error_at(self.position(), kind, desc)
+    }
+
     /// Checks that there are at least `size` bytes remaining. Always call this before reading
     /// anything as [`Bytes`]'s methods will panic if there is insufficient data.
     pub fn need(&self, size: usize) -> wad::Result<()> {
         if self.len() >= size {
             Ok(())
         } else {
-            Err(self.lump.error("not enough data"))
+            let desc = format!("need {} bytes, only {} left", size, self.len());
+            Err(self.error(MalformedKind::Truncated, desc))
         }
     }
 
@@ -84,10 +164,43 @@ impl Cursor<'_> {
         if self.is_empty() {
             Ok(())
         } else {
-            Err(self.lump.error("too much data"))
+            let desc = format!("{} bytes of trailing data", self.len());
+            Err(self.error(MalformedKind::TrailingData, desc))
         }
     }
 
+    /// Checks that there's enough data, then reads a fixed-size [`Record`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let lump = dusty_room::wad::Wad::load("")?.lump("")?;
+    /// # let mut cursor = lump.cursor();
+    /// #
+    /// let value: u32 = cursor.read()?;
+    /// let [x, y] = cursor.read::<[i16; 2]>()?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    pub fn read<R: Record>(&mut self) -> wad::Result<R> {
+        self.need(R::SIZE)?;
+        let raw = self.split_to(R::SIZE);
+        Ok(R::decode(&mut &raw[..]))
+    }
+
+    /// Checks that there's enough data, then reads `count` consecutive [`Record`]s, such as a
+    /// table of little-endian offsets.
+    pub fn read_array<R: Record>(&mut self, count: usize) -> wad::Result<Vec<R>> {
+        let size = count
+            .checked_mul(R::SIZE)
+            .ok_or_else(|| self.error(MalformedKind::BadValue, format!("bad count {}", count)))?;
+        self.need(size)?;
+
+        let raw = self.split_to(size);
+        let mut raw = &raw[..];
+        Ok((0..count).map(|_| R::decode(&mut raw)).collect())
+    }
+
     /// Reads an 8-byte, NUL padded name.
     ///
     /// # Examples
@@ -105,8 +218,8 @@ impl Cursor<'_> {
     /// # Panics
     ///
     /// Panics if there are fewer than 8 bytes remaining.
-    pub fn get_name(&mut self) -> String {
-        parse_name(self.split_to(8).as_ref().try_into().unwrap())
+    pub fn get_name(&mut self) -> LumpName {
+        LumpName::from_raw(self.split_to(8).as_ref().try_into().unwrap())
     }
 }
 
@@ -123,3 +236,48 @@ impl DerefMut for Cursor<'_> {
         &mut self.data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{WadBuilder, WadKind};
+
+    #[test]
+    fn sub_cursors() -> wad::Result<()> {
+        let data: Vec<u8> = (0..10).collect();
+        let file = WadBuilder::new(WadKind::Pwad).lump("DATA", data).build_file("test.wad")?;
+        let lump = file.lump("DATA")?;
+        let mut cursor = lump.cursor();
+
+        assert_eq!(cursor.read_array::<u8>(2)?, [0, 1]);
+        assert_eq!(cursor.position(), 2);
+
+        let mut split = cursor.split(4)?;
+        assert_eq!(cursor.position(), 6);
+        assert_eq!(split.position(), 2);
+        assert_eq!(split.read::<u16>()?, 0x0302);
+
+        // Sub-cursors can seek within their bounds but not outside them.
+        split.seek(5)?;
+        assert_eq!(split.read::<u8>()?, 5);
+        assert_matches!(split.read::<u8>(), Err(wad::Error::Malformed { .. }));
+        assert_eq!(split.at(1).err().unwrap().kind(), Some(MalformedKind::OutOfBounds));
+        assert_eq!(split.at(6)?.len(), 0);
+
+        let mut at = cursor.at(8)?;
+        assert_eq!(at.read::<[u8; 2]>()?, [8, 9]);
+        at.done()?;
+
+        cursor.seek(0)?;
+        let err = cursor.read_array::<u32>(3).unwrap_err();
+        assert_eq!(err.location().unwrap().offset, Some(0));
+        assert_eq!(err.to_string(), "test.wad: DATA at offset 0: need 12 bytes, only 10 left");
+
+        cursor.seek(9)?;
+        let err = cursor.done().unwrap_err();
+        assert_eq!(err.kind(), Some(MalformedKind::TrailingData));
+        assert_eq!(err.location().unwrap().offset, Some(9));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::wad;
+
+/// Decides what loaders do when they run into malformed data, and collects the problems they
+/// recover from.
+///
+/// Loaders are strict by default and fail on the first problem. Plenty of released PWADs have
+/// defects that vanilla DOOM shrugs off, though: oddball lump names, trailing garbage, references
+/// to textures that don't exist. A lenient `Diagnostics` lets loading carry on past these. The
+/// loader substitutes a sensible default, records the problem as a warning, and keeps going.
+/// Problems that can't be worked around are still errors.
+///
+/// Loaders that support lenient loading have a `load_with` variant that takes a `Diagnostics`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dusty_room::assets::Assets;
+/// use dusty_room::wad::{Diagnostics, Wad};
+///
+/// let mut diagnostics = Diagnostics::lenient();
+/// let wad = Wad::load("doom2.wad")?.patch_with("sloppy.wad", &mut diagnostics)?;
+/// let assets = Assets::load_with(&wad, &mut diagnostics)?;
+///
+/// for warning in diagnostics.warnings() {
+///     eprintln!("warning: {}", warning);
+/// }
+/// #
+/// # Ok::<(), dusty_room::wad::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    lenient: bool,
+    warnings: Vec<wad::Error>,
+}
+
+impl Diagnostics {
+    /// Fails on the first problem. This is what the plain `load` functions use.
+    pub fn strict() -> Self {
+        Self { lenient: false, warnings: Vec::new() }
+    }
+
+    /// Recovers from problems where possible and records them as warnings.
+    pub fn lenient() -> Self {
+        Self { lenient: true, warnings: Vec::new() }
+    }
+
+    /// Checks if loaders should try to recover from problems.
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Reports a problem that the caller knows how to recover from. In strict mode the error is
+    /// returned so the caller can bail out with `?`. In lenient mode it's recorded as a warning
+    /// and the caller should carry on.
+    pub fn report(&mut self, err: wad::Error) -> wad::Result<()> {
+        if self.lenient {
+            self.warnings.push(err);
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Unwraps `result`, or if it's an error [reports] it and falls back to a default value.
+    ///
+    /// [reports]: Self::report
+    pub fn recover<T>(
+        &mut self,
+        result: wad::Result<T>,
+        fallback: impl FnOnce() -> T,
+    ) -> wad::Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.report(err)?;
+                Ok(fallback())
+            }
+        }
+    }
+
+    /// The warnings recorded so far, oldest first.
+    pub fn warnings(&self) -> &[wad::Error] {
+        &self.warnings
+    }
+
+    /// Consumes the diagnostics and returns the recorded warnings.
+    pub fn into_warnings(self) -> Vec<wad::Error> {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::MalformedKind;
+
+    fn oops() -> wad::Error {
+        wad::Error::malformed("a.wad", MalformedKind::BadValue, "oops")
+    }
+
+    #[test]
+    fn report() {
+        let mut strict = Diagnostics::strict();
+        assert_matches!(strict.report(oops()), Err(_));
+        assert_matches!(strict.recover(Err(oops()), || 1), Err(_));
+        assert!(strict.warnings().is_empty());
+
+        let mut lenient = Diagnostics::lenient();
+        assert_matches!(lenient.report(oops()), Ok(()));
+        assert_matches!(lenient.recover(Err(oops()), || 1), Ok(1));
+        assert_matches!(lenient.recover(Ok(2), || 1), Ok(2));
+        assert_eq!(lenient.into_warnings().len(), 2);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::wad::folders::FolderLayout;
+use crate::wad::{self, Diagnostics, WadFile};
+
+impl WadFile {
+    /// Loads a directory of loose files as if it were a [PWAD]. This is handy while working on a
+    /// mod since there's no need to rebuild a WAD after every change: just reload the directory.
+    ///
+    /// The directory is laid out the same way as a [PK3] archive. Files in subfolders like
+    /// `flats/`, `sprites/`, and `patches/` are placed between the appropriate [markers], files in
+    /// the top-level directory become ordinary lumps, and WAD files in `maps/` are inlined as map
+    /// blocks. For example:
+    ///
+    /// ```text
+    /// my-mod/
+    ///   PLAYPAL.lmp
+    ///   flats/FLOOR4_8.lmp
+    ///   patches/WALL00_1.lmp
+    ///   maps/MAP01.wad
+    /// ```
+    ///
+    /// [`Wad::patch`] and [`Wad::patch_unchecked`] call this automatically when given a
+    /// directory.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::Wad;
+    ///
+    /// let wad = Wad::load("doom2.wad")?.patch("my-mod/")?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [PWAD]: crate::wad::WadKind::Pwad
+    /// [PK3]: Self::load_pk3
+    /// [markers]: crate::wad::Namespace::markers
+    /// [`Wad::patch`]: crate::wad::Wad::patch
+    /// [`Wad::patch_unchecked`]: crate::wad::Wad::patch_unchecked
+    pub fn load_dir(path: impl AsRef<Path>) -> wad::Result<Arc<Self>> {
+        Self::load_dir_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Loads a directory of loose files as if it were a PWAD. If `diagnostics` is [lenient],
+    /// files and subdirectories that can't be read and map WADs that can't be loaded are reported
+    /// and left out.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_dir_with(
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let path = path.as_ref();
+        let mut layout = FolderLayout::new(path);
+        let mut visited = HashSet::new();
+
+        Self::read_dir(path, "", &mut layout, &mut visited, diagnostics)?;
+
+        layout.build(diagnostics)
+    }
+
+    /// Recursively reads every file in `dir` into the layout. `prefix` is the `/`-separated path of
+    /// `dir` relative to the root. `visited` holds the canonical paths of the directories read so
+    /// far.
+    ///
+    /// It's an error if `dir` itself can't be listed. Problems with its contents are reported to
+    /// `diagnostics`.
+    fn read_dir(
+        dir: &Path,
+        prefix: &str,
+        layout: &mut FolderLayout,
+        visited: &mut HashSet<PathBuf>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<()> {
+        let io_error = |path: &Path, err| wad::Error::Io { path: path.to_owned(), source: err };
+
+        // Symlinks are followed, so a link back up the tree would recurse forever. Read each
+        // directory once, no matter how many ways there are to reach it.
+        let canonical = fs::canonicalize(dir).map_err(|err| io_error(dir, err))?;
+        if !visited.insert(canonical) {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir).map_err(|err| io_error(dir, err))? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    diagnostics.report(io_error(dir, err))?;
+                    continue;
+                }
+            };
+            let path = entry.path();
+
+            // Skip file names that aren't valid Unicode. They can't be legal lump names anyway.
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let relative_path = format!("{}{}", prefix, name);
+
+            // Follow symlinks to files and directories alike. Cycles are caught above.
+            let result = match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => {
+                    let prefix = format!("{}/", relative_path);
+                    Self::read_dir(&path, &prefix, layout, visited, diagnostics)
+                }
+                Ok(_) => match fs::read(&path) {
+                    Ok(data) => {
+                        layout.add(&relative_path, Bytes::from(data));
+                        Ok(())
+                    }
+                    Err(err) => Err(io_error(&path, err)),
+                },
+                Err(err) => Err(io_error(&path, err)),
+            };
+
+            if let Err(err) = result {
+                diagnostics.report(err)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::wad::{Wad, WadBuilder, WadKind};
+
+    #[test]
+    fn load() -> wad::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("flats")).unwrap();
+        fs::create_dir_all(root.join("maps")).unwrap();
+        fs::write(root.join("flats/FLOOR4_8.lmp"), [1; 4096]).unwrap();
+        fs::write(root.join("playpal.lmp"), [2; 768]).unwrap();
+        fs::write(root.join(".hidden"), [3; 10]).unwrap();
+
+        let mut map = WadBuilder::new(WadKind::Pwad);
+        map.map("MAP01", [("THINGS", vec![4; 10])]);
+        map.save(root.join("maps/MAP02.wad"))?;
+
+        let mut base = WadBuilder::new(WadKind::Iwad);
+        base.lump("PLAYPAL", vec![0; 768]);
+        let wad = Wad::new(base.build_file("base.wad")?)?.patch(root)?;
+
+        assert_eq!(wad.lump("PLAYPAL")?.data(), [2; 768]);
+        assert_eq!(wad.lumps_between("F_START", "F_END")?[1].name(), "FLOOR4_8");
+        assert_eq!(wad.lumps_following("MAP02", 2)?[1].data(), [4; 10]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient() -> wad::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("maps")).unwrap();
+        fs::write(root.join("maps/MAP01.wad"), "not a WAD").unwrap();
+        fs::write(root.join("DEMO1.lmp"), [1; 10]).unwrap();
+
+        assert_matches!(WadFile::load_dir(root), Err(wad::Error::Malformed { .. }));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let file = WadFile::load_with(root, &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 1);
+        assert_eq!(file.lump("DEMO1")?.data(), [1; 10]);
+        assert!(file.try_lump("MAP01")?.is_none());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop() -> wad::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("flats")).unwrap();
+        fs::write(root.join("flats/FLOOR4_8.lmp"), [1; 4096]).unwrap();
+        std::os::unix::fs::symlink(root, root.join("flats/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("flats"), root.join("sprites")).unwrap();
+
+        // The flat is only read once even though there are infinitely many paths to it.
+        let file = WadFile::load_dir(root)?;
+        let names = file.lumps().map(|lump| lump.name()).collect::<Vec<_>>();
+        assert_eq!(names.iter().filter(|&&name| name == "FLOOR4_8").count(), 1);
+
+        Ok(())
+    }
+}
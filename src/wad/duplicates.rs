@@ -0,0 +1,41 @@
+/// How lookups by name handle a lump name that appears more than once in the same file.
+///
+/// Duplicate names are common in the wild. Vanilla DOOM doesn't care: it searches the directory
+/// from the end and takes the last match. Tools that check WADs for mistakes want to hear about
+/// them instead.
+///
+/// The policy applies to the [`lump`], [`try_lump`], [`lumps_following`], and [`lumps_between`]
+/// families of lookups. Set it with [`Wad::with_duplicate_policy`], or pass it to a standalone
+/// [`WadFile`]'s lookups such as [`WadFile::try_lump_with`].
+///
+/// [`lump`]: crate::wad::Wad::lump
+/// [`try_lump`]: crate::wad::Wad::try_lump
+/// [`lumps_following`]: crate::wad::Wad::lumps_following
+/// [`lumps_between`]: crate::wad::Wad::lumps_between
+/// [`Wad::with_duplicate_policy`]: crate::wad::Wad::with_duplicate_policy
+/// [`WadFile`]: crate::wad::WadFile
+/// [`WadFile::try_lump_with`]: crate::wad::WadFile::try_lump_with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Duplicates are an error, unless every copy has identical, non-empty content. The IWADs
+    /// shipped with a few accidental duplications of this kind, so they are allowed. This is the
+    /// default.
+    #[default]
+    Error,
+
+    /// Use the first copy.
+    First,
+
+    /// Use the last copy, like vanilla DOOM.
+    Last,
+
+    /// Use the last copy and record a warning, unless every copy has identical, non-empty
+    /// content. Warnings are recorded in the [lenient] [`Diagnostics`] passed to lookups like
+    /// [`Wad::lump_with`]. Lookups without a `Diagnostics`, or with a strict one, have nowhere to
+    /// keep the warning, so they treat a duplicate as an error instead.
+    ///
+    /// [lenient]: crate::wad::Diagnostics::lenient
+    /// [`Diagnostics`]: crate::wad::Diagnostics
+    /// [`Wad::lump_with`]: crate::wad::Wad::lump_with
+    LastWithWarning,
+}
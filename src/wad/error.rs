@@ -1,10 +1,10 @@
 use std::borrow::Cow;
-use std::io;
 use std::path::{Path, PathBuf};
+use std::{fmt, io};
 
 use thiserror::Error;
 
-use crate::wad::WadKind;
+use crate::wad::{LumpName, WadKind};
 
 /// A specialized [`Result`] type for [`Wad`] and [`WadFile`] operations. This typedef is used to
 /// avoid writing out [`wad::Error`] directly and is otherwise a direct mapping to [`Result`].
@@ -35,10 +35,14 @@ pub enum Error {
     ///
     /// [`Wad`]: crate::wad::Wad
     /// [`WadFile`]: crate::wad::WadFile
-    #[error("{}: {desc}", path.display())]
+    #[error("{}{}: {desc}", path.display(), DisplayLocation(location))]
     Malformed {
         /// The path of the malformed file.
         path: PathBuf,
+        /// What sort of problem it is.
+        kind: MalformedKind,
+        /// The lump where the problem was found, if it can be pinned down to one.
+        location: Option<ErrorLocation>,
         /// A description of the error.
         desc: Cow<'static, str>,
     },
@@ -60,8 +64,111 @@ pub enum Error {
 }
 
 impl Error {
-    /// Creates an [`Error::Malformed`]. Accepts both `&'static str` literals and owned `String`s.
-    pub fn malformed(path: impl AsRef<Path>, desc: impl Into<Cow<'static, str>>) -> Self {
-        Self::Malformed { path: path.as_ref().to_owned(), desc: desc.into() }
+    /// Creates an [`Error::Malformed`] that isn't tied to a particular lump. Accepts both
+    /// `&'static str` literals and owned `String`s.
+    pub fn malformed(
+        path: impl AsRef<Path>,
+        kind: MalformedKind,
+        desc: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self::Malformed { path: path.as_ref().to_owned(), kind, location: None, desc: desc.into() }
+    }
+
+    /// Creates an [`Error::Malformed`] blaming a particular lump.
+    pub fn malformed_at(
+        path: impl AsRef<Path>,
+        kind: MalformedKind,
+        location: ErrorLocation,
+        desc: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self::Malformed {
+            path: path.as_ref().to_owned(),
+            kind,
+            location: Some(location),
+            desc: desc.into(),
+        }
+    }
+
+    /// What sort of problem a [`Malformed`] error is. Returns `None` for other errors.
+    ///
+    /// [`Malformed`]: Self::Malformed
+    pub fn kind(&self) -> Option<MalformedKind> {
+        match self {
+            Self::Malformed { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// Where a [`Malformed`] error was found, if it can be pinned down to a particular lump.
+    ///
+    /// [`Malformed`]: Self::Malformed
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match self {
+            Self::Malformed { location, .. } => location.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Classifies [`Error::Malformed`] errors so tools can act on them without parsing error messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MalformedKind {
+    /// The file isn't a WAD, or its header or archive structure is unreadable.
+    BadHeader,
+
+    /// The data ends partway through a record.
+    Truncated,
+
+    /// There's unexpected data after the last record.
+    TrailingData,
+
+    /// An offset or size points outside the file or lump.
+    OutOfBounds,
+
+    /// A lump name contains illegal characters.
+    BadName,
+
+    /// A lump name that should be unique appears more than once.
+    Duplicate,
+
+    /// A required lump or marker is missing.
+    Missing,
+
+    /// Lumps or markers are in the wrong order.
+    BadOrder,
+
+    /// A record refers to something that doesn't exist, like a missing texture or an out of range
+    /// sector number.
+    BadReference,
+
+    /// A field has an invalid value.
+    BadValue,
+}
+
+/// Pinpoints where an [`Error::Malformed`] error was found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ErrorLocation {
+    /// The lump's name.
+    pub lump: LumpName,
+
+    /// The lump's index in the file's lump directory.
+    pub index: usize,
+
+    /// The byte offset within the lump, if the problem is with a particular part of it.
+    pub offset: Option<usize>,
+}
+
+/// Formats an optional location as a prefix for an error message.
+struct DisplayLocation<'a>(&'a Option<ErrorLocation>);
+
+impl fmt::Display for DisplayLocation<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            None => Ok(()),
+            Some(ErrorLocation { lump, offset: None, .. }) => write!(fmt, ": {}", lump),
+            Some(ErrorLocation { lump, offset: Some(offset), .. }) => {
+                write!(fmt, ": {} at offset {}", lump, offset)
+            }
+        }
     }
 }
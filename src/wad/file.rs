@@ -9,13 +9,17 @@ use std::io::Seek;
 use std::io::SeekFrom;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 use std::{fmt, io};
 
 use bytes::Bytes;
+use md5::{Digest, Md5};
+use memmap2::Mmap;
 
-use crate::wad::parse_name;
-use crate::wad::{self, Lump, Lumps};
+use crate::wad::{
+    self, compact, game, lint, CompactOptions, Compaction, Diagnostics, DuplicatePolicy,
+    ErrorLocation, Game, LintReport, Lump, LumpName, Lumps, MalformedKind, Namespace, ToLumpName,
+};
 
 /// A single IWAD or PWAD.
 ///
@@ -26,12 +30,28 @@ use crate::wad::{self, Lump, Lumps};
 /// [`Wad`]: crate::wad::Wad
 pub struct WadFile {
     path: PathBuf,
-    raw: Bytes,
+    storage: Storage,
     kind: WadKind,
+    directory_offset: usize,
+    file_size: usize,
     lump_locations: Vec<LumpLocation>,
-    lump_indices: HashMap<String, Vec<usize>>,
+    lump_indices: HashMap<LumpName, Vec<usize>>,
+    game: OnceLock<Option<Game>>,
+    lump_namespaces: OnceLock<Vec<Namespace>>,
 }
 
+/// Where lump data comes from.
+enum Storage {
+    /// The entire file has been read into memory, or memory mapped.
+    Loaded(Bytes),
+
+    /// Lumps are read from the underlying reader the first time they're needed and then cached.
+    Lazy { reader: Mutex<Box<dyn ReadSeek>>, cache: Vec<OnceLock<Bytes>> },
+}
+
+trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
 #[derive(Debug)]
 struct Header {
     pub kind: WadKind,
@@ -54,23 +74,96 @@ pub enum WadKind {
 #[derive(Debug)]
 struct Directory {
     pub lump_locations: Vec<LumpLocation>,
-    pub lump_indices: HashMap<String, Vec<usize>>,
+    pub lump_indices: HashMap<LumpName, Vec<usize>>,
 }
 
 #[derive(Debug)]
-struct LumpLocation {
+pub(super) struct LumpLocation {
     pub offset: usize,
     pub size: usize,
-    pub name: String,
+    pub name: LumpName,
 }
 
 impl WadFile {
     /// Loads a WAD file from disk.
+    ///
+    /// If `path` is a directory it's loaded as a [loose directory] instead.
+    ///
+    /// [loose directory]: Self::load_dir
     pub fn load(path: impl AsRef<Path>) -> wad::Result<Arc<Self>> {
+        Self::load_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Loads a WAD file from disk, recovering from a malformed lump directory if `diagnostics` is
+    /// [lenient]:
+    ///
+    /// * Lumps with illegal names keep their names as is.
+    /// * Lumps that point past the end of the file are truncated.
+    /// * A truncated directory ends at the last complete entry.
+    ///
+    /// Directories are loaded with [`load_dir_with`].
+    ///
+    /// [lenient]: Diagnostics::lenient
+    /// [`load_dir_with`]: Self::load_dir_with
+    pub fn load_with(
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
         let path = path.as_ref();
-        let file = File::open(path)
-            .map_err(|err| wad::Error::Io { path: path.to_owned(), source: err })?;
-        Self::load_reader(path, file)
+        if path.is_dir() {
+            return Self::load_dir_with(path, diagnostics);
+        }
+        let io_error = |err| wad::Error::Io { path: path.to_owned(), source: err };
+
+        let file = File::open(path).map_err(io_error)?;
+        let raw = Self::read_bytes(file).map_err(io_error)?;
+        Self::load_raw_with(path, raw, diagnostics)
+    }
+
+    /// Loads a WAD file from disk by memory mapping it rather than reading it into memory. Lump
+    /// data is handed out as zero-copy slices of the mapping, so only the pages that are actually
+    /// used get read from disk. This makes startup much faster for large megawads.
+    ///
+    /// The mapping stays alive as long as any [`Lump`] or slice of lump data is still around.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{Wad, WadFile, WadKind};
+    ///
+    /// let file = WadFile::load_mmap("doom2.wad")?;
+    /// file.expect_kind(WadKind::Iwad)?;
+    /// let wad = Wad::new(file)?.add(WadFile::load_mmap("megawad.wad")?)?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// # Caveats
+    ///
+    /// Modifying the file on disk while it's mapped can cause lump data to change out from under
+    /// you, or even crash the program on some platforms. Don't use this for files that other
+    /// programs might be editing.
+    pub fn load_mmap(path: impl AsRef<Path>) -> wad::Result<Arc<Self>> {
+        Self::load_mmap_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Memory maps a WAD file, recovering from a malformed lump directory if `diagnostics` is
+    /// lenient. See [`load_mmap`] and [`load_with`] for details.
+    ///
+    /// [`load_mmap`]: Self::load_mmap
+    /// [`load_with`]: Self::load_with
+    pub fn load_mmap_with(
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let path = path.as_ref();
+        let io_error = |err| wad::Error::Io { path: path.to_owned(), source: err };
+
+        let file = File::open(path).map_err(io_error)?;
+        // SAFETY: The mapping is read-only. Undefined behavior can only occur if the file is
+        // modified externally while it is mapped, which is documented above.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+        Self::load_raw_with(path, Bytes::from_owner(mmap), diagnostics)
     }
 
     /// Loads a WAD file from a generic reader.
@@ -81,10 +174,22 @@ impl WadFile {
     /// The `path` only used for display purposes, such as in error messages. It doesn't need to
     /// point to an actual file on disk.
     pub fn load_reader(path: impl AsRef<Path>, file: impl Read + Seek) -> wad::Result<Arc<Self>> {
+        Self::load_reader_with(path, file, &mut Diagnostics::strict())
+    }
+
+    /// Loads a WAD file from a generic reader, recovering from a malformed lump directory if
+    /// `diagnostics` is lenient. See [`load_with`] for details.
+    ///
+    /// [`load_with`]: Self::load_with
+    pub fn load_reader_with(
+        path: impl AsRef<Path>,
+        file: impl Read + Seek,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
         let path = path.as_ref();
         let raw = Self::read_bytes(file)
             .map_err(|err| wad::Error::Io { path: path.to_owned(), source: err })?;
-        Self::load_raw(path, raw)
+        Self::load_raw_with(path, raw, diagnostics)
     }
 
     fn read_bytes(mut file: impl Read + Seek) -> io::Result<Bytes> {
@@ -110,23 +215,165 @@ impl WadFile {
         Ok(raw)
     }
 
-    /// Loads a WAD file from a raw byte buffer.
+    /// Opens a WAD file from disk without reading its lumps. Only the header and lump directory are
+    /// read up front. Lump data is read from disk the first time it's requested and then cached.
+    ///
+    /// This is a good choice when only a handful of lumps are needed from a large file. Since the
+    /// file stays open, lookups can fail with I/O errors.
+    pub fn load_lazy(path: impl AsRef<Path>) -> wad::Result<Arc<Self>> {
+        Self::load_lazy_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Opens a WAD file lazily, recovering from a malformed lump directory if `diagnostics` is
+    /// lenient. See [`load_lazy`] and [`load_with`] for details.
+    ///
+    /// [`load_lazy`]: Self::load_lazy
+    /// [`load_with`]: Self::load_with
+    pub fn load_lazy_with(
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| wad::Error::Io { path: path.to_owned(), source: err })?;
+        Self::load_reader_lazy_with(path, file, diagnostics)
+    }
+
+    /// Opens a WAD file from a generic reader without reading its lumps. Only the header and lump
+    /// directory are read up front. Lump data is read from the reader the first time it's
+    /// requested and then cached.
+    ///
+    /// The reader's current position doesn't matter. Reading WAD files requires seeking to
+    /// arbitrary offsets throughout the file.
     ///
     /// The `path` only used for display purposes, such as in error messages. It doesn't need to
     /// point to an actual file on disk.
-    pub fn load_raw(path: impl AsRef<Path>, raw: Bytes) -> wad::Result<Arc<Self>> {
-        Self::load_raw_impl(path.as_ref(), raw)
-            .map_err(|desc: String| wad::Error::malformed(path, desc))
+    pub fn load_reader_lazy(
+        path: impl AsRef<Path>,
+        file: impl Read + Seek + Send + 'static,
+    ) -> wad::Result<Arc<Self>> {
+        Self::load_reader_lazy_with(path, file, &mut Diagnostics::strict())
+    }
+
+    /// Opens a WAD file lazily from a generic reader, recovering from a malformed lump directory
+    /// if `diagnostics` is lenient. See [`load_reader_lazy`] and [`load_with`] for details.
+    ///
+    /// [`load_reader_lazy`]: Self::load_reader_lazy
+    /// [`load_with`]: Self::load_with
+    pub fn load_reader_lazy_with(
+        path: impl AsRef<Path>,
+        file: impl Read + Seek + Send + 'static,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        Self::load_lazy_impl(path.as_ref(), Box::new(file), diagnostics)
     }
 
     // Non-generic helper to minimize the amount of code subject to monomorphization.
-    fn load_raw_impl(path: &Path, raw: Bytes) -> Result<Arc<Self>, String> {
-        let Header { kind, lump_count, directory_offset } = Self::read_header(&raw)?;
+    fn load_lazy_impl(
+        path: &Path,
+        mut file: Box<dyn ReadSeek>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let io_error = |err| wad::Error::Io { path: path.to_owned(), source: err };
+
+        let file_size = file.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let file_size: usize = file_size.try_into().unwrap_or(usize::MAX);
+
+        let mut header = Vec::with_capacity(12);
+        file.rewind().map_err(io_error)?;
+        file.by_ref().take(12).read_to_end(&mut header).map_err(io_error)?;
+        let Header { kind, lump_count, directory_offset } = Self::read_header(&header)
+            .map_err(|desc| wad::Error::malformed(path, MalformedKind::BadHeader, desc))?;
+
+        // Don't trust the lump count. Never read past the end of the file.
+        let directory_size =
+            lump_count.saturating_mul(16).min(file_size.saturating_sub(directory_offset));
+        let mut directory = Vec::with_capacity(directory_size);
+        if directory_offset <= file_size {
+            file.seek(SeekFrom::Start(directory_offset as u64)).map_err(io_error)?;
+            file.by_ref()
+                .take(directory_size as u64)
+                .read_to_end(&mut directory)
+                .map_err(io_error)?;
+        }
+
+        let Directory { lump_locations, lump_indices } = Self::read_directory(
+            path,
+            &directory,
+            lump_count,
+            directory_offset,
+            file_size,
+            diagnostics,
+        )?;
+
+        let cache = lump_locations.iter().map(|_| OnceLock::new()).collect();
+        let storage = Storage::Lazy { reader: Mutex::new(file), cache };
+
+        Ok(Arc::new(Self {
+            path: path.to_owned(),
+            storage,
+            kind,
+            directory_offset,
+            file_size,
+            lump_locations,
+            lump_indices,
+            game: OnceLock::new(),
+            lump_namespaces: OnceLock::new(),
+        }))
+    }
+
+    /// Loads a WAD file from a raw byte buffer.
+    ///
+    /// The `path` only used for display purposes, such as in error messages. It doesn't need to
+    /// point to an actual file on disk.
+    pub fn load_raw(path: impl AsRef<Path>, raw: Bytes) -> wad::Result<Arc<Self>> {
+        Self::load_raw_with(path, raw, &mut Diagnostics::strict())
+    }
 
-        let Directory { lump_locations, lump_indices } =
-            Self::read_directory(&raw, lump_count, directory_offset)?;
+    /// Loads a WAD file from a raw byte buffer, recovering from a malformed lump directory if
+    /// `diagnostics` is lenient. See [`load_with`] for details.
+    ///
+    /// [`load_with`]: Self::load_with
+    pub fn load_raw_with(
+        path: impl AsRef<Path>,
+        raw: Bytes,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        Self::load_raw_impl(path.as_ref(), raw, diagnostics)
+    }
 
-        Ok(Arc::new(Self { path: path.to_owned(), raw, kind, lump_locations, lump_indices }))
+    // Non-generic helper to minimize the amount of code subject to monomorphization.
+    fn load_raw_impl(
+        path: &Path,
+        raw: Bytes,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let Header { kind, lump_count, directory_offset } = Self::read_header(&raw)
+            .map_err(|desc| wad::Error::malformed(path, MalformedKind::BadHeader, desc))?;
+
+        let file_size = raw.len();
+        let directory = raw.get(directory_offset..).unwrap_or_default();
+        let Directory { lump_locations, lump_indices } = Self::read_directory(
+            path,
+            directory,
+            lump_count,
+            directory_offset,
+            file_size,
+            diagnostics,
+        )?;
+
+        let storage = Storage::Loaded(raw);
+        Ok(Arc::new(Self {
+            path: path.to_owned(),
+            storage,
+            kind,
+            directory_offset,
+            file_size,
+            lump_locations,
+            lump_indices,
+            game: OnceLock::new(),
+            lump_namespaces: OnceLock::new(),
+        }))
     }
 
     fn read_header(raw: &[u8]) -> Result<Header, String> {
@@ -147,47 +394,91 @@ impl WadFile {
         })
     }
 
+    /// Parses the lump directory. `directory` holds the raw bytes starting at `directory_offset`,
+    /// which may be truncated if the file is malformed. Problems are reported to `diagnostics`.
     fn read_directory(
-        raw: &[u8],
-        lump_count: usize,
+        path: &Path,
+        directory: &[u8],
+        mut lump_count: usize,
         directory_offset: usize,
-    ) -> Result<Directory, String> {
-        let mut cursor = raw
-            .get(directory_offset..)
-            .ok_or_else(|| format!("lump directory at bad offset {}", directory_offset))?;
+        file_size: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Directory> {
+        // Reports a problem with the directory entry for the lump `name` at `index`, or with the
+        // directory as a whole if there's no entry.
+        let mut report = |kind, entry: Option<(LumpName, usize)>, desc: String| {
+            let err = match entry {
+                Some((lump, index)) => {
+                    let location = ErrorLocation { lump, index, offset: None };
+                    wad::Error::malformed_at(path, kind, location, desc)
+                }
+                None => wad::Error::malformed(path, kind, desc),
+            };
+            diagnostics.report(err)
+        };
+
+        if directory_offset > file_size {
+            report(
+                MalformedKind::OutOfBounds,
+                None,
+                format!("lump directory at bad offset {}", directory_offset),
+            )?;
+            lump_count = 0;
+        }
+        let mut cursor = directory;
 
         // The WAD is untrusted so clamp how much memory is pre-allocated. For comparison,
         // `doom.wad` has 1,264 lumps and `doom2.wad` has 2,919.
         let mut lump_locations = Vec::with_capacity(lump_count.clamp(0, 4096));
 
-        for _ in 0..lump_count {
+        for index in 0..lump_count {
             // Read the entry and advance the read cursor.
-            let entry = &cursor
-                .get(..16)
-                .ok_or_else(|| format!("lump directory has bad count {}", lump_count))?;
+            let entry = match cursor.get(..16) {
+                Some(entry) => entry,
+                None => {
+                    report(
+                        MalformedKind::Truncated,
+                        None,
+                        format!("lump directory has bad count {}", lump_count),
+                    )?;
+                    break;
+                }
+            };
             cursor = &cursor[16..];
 
             let offset = u32::from_le_bytes(entry[0..4].try_into().unwrap());
             let size = u32::from_le_bytes(entry[4..8].try_into().unwrap());
-            let name: [u8; 8] = entry[8..16].try_into().unwrap();
-            let name = parse_name(&name);
+            let name = LumpName::from_raw(entry[8..16].try_into().unwrap());
 
             // Verify that this is a legal name.
-            let has_illegal_char = name
-                .contains(|ch| !matches!(ch, 'A'..='Z' | '0'..='9' | '[' | ']' | '-' | '_' | '\\'));
-            if name.is_empty() || has_illegal_char {
-                return Err(format!("bad lump name {:?}", name));
+            if !name.is_legal() {
+                report(
+                    MalformedKind::BadName,
+                    Some((name, index)),
+                    format!("bad lump name {:?}", name),
+                )?;
             }
 
-            // Check lump bounds now so we don't have to later.
-            let offset: usize = offset.try_into().unwrap();
-            let size: usize = size.try_into().unwrap();
-
-            if offset >= raw.len() {
-                return Err(format!("{} at bad offset {}", name, offset));
+            // Check lump bounds now so we don't have to later. Out of bounds lumps are truncated.
+            let mut offset: usize = offset.try_into().unwrap();
+            let mut size: usize = size.try_into().unwrap();
+
+            if offset >= file_size {
+                report(
+                    MalformedKind::OutOfBounds,
+                    Some((name, index)),
+                    format!("bad offset {}", offset),
+                )?;
+                offset = file_size;
+                size = 0;
             }
-            if offset + size >= raw.len() {
-                return Err(format!("{} has bad size {}", name, size));
+            if offset + size > file_size {
+                report(
+                    MalformedKind::OutOfBounds,
+                    Some((name, index)),
+                    format!("bad size {}", size),
+                )?;
+                size = file_size - offset;
             }
 
             lump_locations.push(LumpLocation { offset, size, name });
@@ -198,7 +489,7 @@ impl WadFile {
 
         for (index, location) in lump_locations.iter().enumerate() {
             lump_indices
-                .entry(location.name.clone())
+                .entry(location.name)
                 .and_modify(|indices: &mut Vec<usize>| indices.push(index))
                 .or_insert_with(|| vec![index]);
         }
@@ -230,21 +521,49 @@ impl WadFile {
     /// # Errors
     ///
     /// It is an error if the lump is missing.
-    pub fn lump(self: &Arc<Self>, name: &str) -> wad::Result<Lump> {
-        self.try_lump(name)?.ok_or_else(|| self.error(format!("{} missing", name)))
+    pub fn lump(self: &Arc<Self>, name: impl ToLumpName) -> wad::Result<Lump> {
+        self.try_lump(&name)?
+            .ok_or_else(|| self.error(MalformedKind::Missing, format!("{} missing", name)))
+    }
+
+    /// Like [`lump`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded in
+    /// `diagnostics`.
+    ///
+    /// [`lump`]: Self::lump
+    pub fn lump_with(
+        self: &Arc<Self>,
+        name: impl ToLumpName,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lump> {
+        self.try_lump_with(&name, policy, diagnostics)?
+            .ok_or_else(|| self.error(MalformedKind::Missing, format!("{} missing", name)))
     }
 
     /// Retrieves a unique lump by name.
     ///
     /// Returns `Ok(None)` if the lump is missing.
-    pub fn try_lump(self: &Arc<Self>, name: &str) -> wad::Result<Option<Lump>> {
-        let index = self.try_lump_index(name)?;
+    pub fn try_lump(self: &Arc<Self>, name: impl ToLumpName) -> wad::Result<Option<Lump>> {
+        self.try_lump_with(name, DuplicatePolicy::Error, &mut Diagnostics::strict())
+    }
+
+    /// Like [`try_lump`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded in
+    /// `diagnostics`.
+    ///
+    /// [`try_lump`]: Self::try_lump
+    pub fn try_lump_with(
+        self: &Arc<Self>,
+        name: impl ToLumpName,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<Lump>> {
+        let index = self.try_lump_index(name, policy, diagnostics)?;
         if index.is_none() {
             return Ok(None);
         }
         let index = index.unwrap();
 
-        Ok(Some(self.read_lump(index)))
+        Ok(Some(self.read_lump(index)?))
     }
 
     /// Retrieves a block of `size > 0` lumps following a unique named marker. The marker lump is
@@ -257,9 +576,28 @@ impl WadFile {
     /// # Panics
     ///
     /// Panics if `size == 0`.
-    pub fn lumps_following(self: &Arc<Self>, start: &str, size: usize) -> wad::Result<Lumps> {
-        self.try_lumps_following(start, size)?
-            .ok_or_else(|| self.error(format!("{} missing", start)))
+    pub fn lumps_following(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        size: usize,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_following(&start, size)?
+            .ok_or_else(|| self.error(MalformedKind::Missing, format!("{} missing", start)))
+    }
+
+    /// Like [`lumps_following`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded
+    /// in `diagnostics`.
+    ///
+    /// [`lumps_following`]: Self::lumps_following
+    pub fn lumps_following_with(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        size: usize,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_following_with(&start, size, policy, diagnostics)?
+            .ok_or_else(|| self.error(MalformedKind::Missing, format!("{} missing", start)))
     }
 
     /// Retrieves a block of `size > 0` lumps following a unique named marker. The marker lump is
@@ -272,22 +610,41 @@ impl WadFile {
     /// Panics if `size == 0`.
     pub fn try_lumps_following(
         self: &Arc<Self>,
-        start: &str,
+        start: impl ToLumpName,
         size: usize,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lumps_following_with(
+            start,
+            size,
+            DuplicatePolicy::Error,
+            &mut Diagnostics::strict(),
+        )
+    }
+
+    /// Like [`try_lumps_following`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded
+    /// in `diagnostics`.
+    ///
+    /// [`try_lumps_following`]: Self::try_lumps_following
+    pub fn try_lumps_following_with(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        size: usize,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
     ) -> wad::Result<Option<Lumps>> {
         assert!(size > 0);
 
-        let start_index = self.try_lump_index(start)?;
+        let start_index = self.try_lump_index(&start, policy, diagnostics)?;
         if start_index.is_none() {
             return Ok(None);
         }
         let start_index = start_index.unwrap();
 
-        if start_index + size >= self.lump_indices.len() {
-            return Err(self.error(format!("{} missing lumps", start)));
+        if start_index + size > self.lump_locations.len() {
+            return Err(self.lump_error(start_index, MalformedKind::Truncated, "missing lumps"));
         }
 
-        Ok(Some(self.read_lumps(start_index..start_index + size, true)))
+        Ok(Some(self.read_lumps(start_index..start_index + size, true)?))
     }
 
     /// Retrieves a block of lumps between unique start and end markers. The marker lumps are
@@ -296,9 +653,30 @@ impl WadFile {
     /// # Errors
     ///
     /// It is an error if the block is missing.
-    pub fn lumps_between(self: &Arc<Self>, start: &str, end: &str) -> wad::Result<Lumps> {
-        self.try_lumps_between(start, end)?
-            .ok_or_else(|| self.error(format!("{} and {} missing", start, end)))
+    pub fn lumps_between(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_between(&start, &end)?.ok_or_else(|| {
+            self.error(MalformedKind::Missing, format!("{} and {} missing", start, end))
+        })
+    }
+
+    /// Like [`lumps_between`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded in
+    /// `diagnostics`.
+    ///
+    /// [`lumps_between`]: Self::lumps_between
+    pub fn lumps_between_with(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_between_with(&start, &end, policy, diagnostics)?.ok_or_else(|| {
+            self.error(MalformedKind::Missing, format!("{} and {} missing", start, end))
+        })
     }
 
     /// Retrieves a block of lumps between unique start and end markers. The marker lumps are
@@ -307,11 +685,25 @@ impl WadFile {
     /// Returns `Ok(None)` if the block is missing.
     pub fn try_lumps_between(
         self: &Arc<Self>,
-        start: &str,
-        end: &str,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lumps_between_with(start, end, DuplicatePolicy::Error, &mut Diagnostics::strict())
+    }
+
+    /// Like [`try_lumps_between`] but with a choice of [`DuplicatePolicy`]. Warnings are recorded
+    /// in `diagnostics`.
+    ///
+    /// [`try_lumps_between`]: Self::try_lumps_between
+    pub fn try_lumps_between_with(
+        self: &Arc<Self>,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
     ) -> wad::Result<Option<Lumps>> {
-        let start_index = self.try_lump_index(start)?;
-        let end_index = self.try_lump_index(end)?;
+        let start_index = self.try_lump_index(&start, policy, diagnostics)?;
+        let end_index = self.try_lump_index(&end, policy, diagnostics)?;
 
         match (start_index, end_index) {
             (Some(_), Some(_)) => {}
@@ -321,11 +713,15 @@ impl WadFile {
             }
 
             (Some(_), None) => {
-                return Err(self.error(format!("{} without {}", start, end)));
+                return Err(
+                    self.error(MalformedKind::Missing, format!("{} without {}", start, end))
+                );
             }
 
             (None, Some(_)) => {
-                return Err(self.error(format!("{} without {}", end, start)));
+                return Err(
+                    self.error(MalformedKind::Missing, format!("{} without {}", end, start))
+                );
             }
         }
 
@@ -333,10 +729,127 @@ impl WadFile {
         let end_index = end_index.unwrap();
 
         if start_index > end_index {
-            return Err(self.error(format!("{} after {}", start, end)));
+            return Err(self.error(MalformedKind::BadOrder, format!("{} after {}", start, end)));
+        }
+
+        Ok(Some(self.read_lumps(start_index..end_index + 1, false)?))
+    }
+
+    /// Retrieves all of the lumps in a [`Namespace`], in directory order. Marker lumps, including
+    /// the IWADs' numbered sub-markers like `F1_START`, are not included. Returns an empty list if
+    /// the file has no lumps in the namespace.
+    ///
+    /// Unlike [`lumps_between`], this accepts any of the namespace's [markers] and any mix of
+    /// them, so a block that starts with `FF_START` and ends with `F_END` is fine. A file can also
+    /// contain more than one block for the same namespace.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if the file's namespace markers are unbalanced.
+    ///
+    /// [`lumps_between`]: Self::lumps_between
+    /// [markers]: Namespace::markers
+    pub fn namespace(self: &Arc<Self>, namespace: Namespace) -> wad::Result<Vec<Lump>> {
+        self.namespace_members(namespace)?.into_iter().map(|index| self.read_lump(index)).collect()
+    }
+
+    /// Like [`namespace`], but with duplicate names settled as [`namespace_indices`] does.
+    ///
+    /// [`namespace`]: Self::namespace
+    /// [`namespace_indices`]: Self::namespace_indices
+    pub(super) fn namespace_with(
+        self: &Arc<Self>,
+        namespace: Namespace,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Vec<Lump>> {
+        self.namespace_indices(namespace, policy, diagnostics)?
+            .into_iter()
+            .map(|index| self.read_lump(index))
+            .collect()
+    }
+
+    /// The indices of the lumps in a namespace, not counting markers.
+    fn namespace_members(&self, namespace: Namespace) -> wad::Result<Vec<usize>> {
+        let namespaces = self.namespaces()?;
+
+        Ok(self
+            .lump_locations
+            .iter()
+            .zip(namespaces)
+            .enumerate()
+            .filter(|(_, (location, ns))| {
+                *ns == namespace
+                    && Namespace::from_marker(location.name).is_none()
+                    && !Namespace::is_sub_marker(location.name)
+            })
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Determines which namespace each lump is in. Marker lumps are considered part of the
+    /// namespace they delimit.
+    pub(super) fn namespaces(&self) -> wad::Result<Vec<Namespace>> {
+        self.namespaces_with(&mut Diagnostics::strict())
+    }
+
+    /// Like [`namespaces`], but if `diagnostics` is lenient, unbalanced markers are reported and
+    /// then ignored.
+    ///
+    /// [`namespaces`]: Self::namespaces
+    pub(super) fn namespaces_with(
+        &self,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Vec<Namespace>> {
+        let mut namespaces = Vec::with_capacity(self.lump_locations.len());
+        // The open namespace, the name of its start marker, and how deeply its markers are nested.
+        let mut open: Option<(Namespace, LumpName, usize)> = None;
+
+        for (index, location) in self.lump_locations.iter().enumerate() {
+            let name = location.name;
+
+            match (Namespace::from_marker(name), open) {
+                // Non-markers go in the open namespace, if any.
+                (None, Some((namespace, _, _))) => namespaces.push(namespace),
+                (None, None) => namespaces.push(Namespace::Global),
+
+                // Start a new namespace.
+                (Some((namespace, true)), None) => {
+                    open = Some((namespace, name, 1));
+                    namespaces.push(namespace);
+                }
+
+                // Some PWADs nest alternate markers, like `FF_START` inside `F_START`.
+                (Some((namespace, true)), Some((outer, start, depth))) if namespace == outer => {
+                    open = Some((outer, start, depth + 1));
+                    namespaces.push(namespace);
+                }
+
+                (Some((namespace, false)), Some((outer, start, depth))) if namespace == outer => {
+                    open = if depth > 1 { Some((outer, start, depth - 1)) } else { None };
+                    namespaces.push(namespace);
+                }
+
+                (Some(_), Some((outer, start, _))) => {
+                    let desc = format!("inside {} block", start);
+                    diagnostics.report(self.lump_error(index, MalformedKind::BadOrder, desc))?;
+                    namespaces.push(outer);
+                }
+
+                (Some((_, false)), None) => {
+                    let desc = "no matching start marker";
+                    diagnostics.report(self.lump_error(index, MalformedKind::Missing, desc))?;
+                    namespaces.push(Namespace::Global);
+                }
+            }
+        }
+
+        if let Some((_, start, _)) = open {
+            let desc = format!("{} without end marker", start);
+            diagnostics.report(self.error(MalformedKind::Missing, desc))?;
         }
 
-        Ok(Some(self.read_lumps(start_index..end_index + 1, false)))
+        Ok(namespaces)
     }
 
     /// Looks up a lump's index.
@@ -345,9 +858,9 @@ impl WadFile {
     ///
     /// # Uniqueness
     ///
-    /// If the lump name isn't unique then that's an error--unless the duplicated lumps have
-    /// identical content. As the [Unofficial Doom Specs] explain, some of the official DOOM wads
-    /// shipped with accidental duplications:
+    /// What happens if the lump name isn't unique depends on the [`DuplicatePolicy`]. By default
+    /// that's an error--unless the duplicated lumps have identical content. As the [Unofficial
+    /// Doom Specs] explain, some of the official DOOM wads shipped with accidental duplications:
     ///
     /// > There are some imperfections in the `DOOM.WAD` file. All versions up to 1.666 have the
     /// > `SW18_7` lump included twice. Versions before 1.666 have the `COMP03_8` lump twice. And
@@ -359,16 +872,21 @@ impl WadFile {
     /// When this happens the last index returned.
     ///
     /// [Unofficial Doom Specs]: http://edge.sourceforge.net/edit_guide/doom_specs.htm
-    fn try_lump_index(self: &Arc<Self>, name: &str) -> wad::Result<Option<usize>> {
-        let mut name = Cow::from(name);
-
-        // Convert the name to uppercase like DOOM does. We have to emulate this because
-        // `doom.wad` and `doom2.wad` include a lowercase `w94_1` in their `PNAMES`.
-        if name.contains(|ch: char| ch.is_ascii_lowercase()) {
-            name.to_mut().make_ascii_uppercase();
-        }
+    pub(super) fn try_lump_index(
+        self: &Arc<Self>,
+        name: impl ToLumpName,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<usize>> {
+        // Names are case insensitive like in DOOM. We have to emulate this because `doom.wad` and
+        // `doom2.wad` include a lowercase `w94_1` in their `PNAMES`. A name that can't be
+        // represented as a `LumpName` can't be in the directory.
+        let name = match name.to_lump_name() {
+            Some(name) => name,
+            None => return Ok(None),
+        };
 
-        match self.lump_indices.get(name.as_ref()).map(Vec::as_slice) {
+        match self.lump_indices.get(&name).map(Vec::as_slice) {
             // Not found.
             None => Ok(None),
 
@@ -376,62 +894,346 @@ impl WadFile {
             Some(&[index]) => Ok(Some(index)),
 
             // Multiple indices.
-            Some(indices) => {
-                let mut lumps: Vec<_> =
-                    indices.iter().map(|&index| self.read_lump(index)).collect();
-                lumps.dedup_by(|l1, l2| l1.data() == l2.data());
-
-                if lumps.len() == 1 && lumps[0].has_data() {
-                    Ok(Some(*indices.last().unwrap()))
-                } else {
-                    Err(self.error(format!("{} found {} times", name, indices.len())))
+            Some(indices) => match self.settle_duplicates(indices, policy)? {
+                (index, None) => Ok(Some(index)),
+                (index, Some(err)) if policy == DuplicatePolicy::LastWithWarning => {
+                    diagnostics.report(err)?;
+                    Ok(Some(index))
+                }
+                (_, Some(err)) => Err(err),
+            },
+        }
+    }
+
+    /// Looks up the index of each distinct lump name in a [`Namespace`], in directory order of
+    /// their first copies.
+    ///
+    /// Duplicate names are settled by `policy`, the same as with [`try_lump_index`], except that
+    /// duplicates the [`DuplicatePolicy::Error`] policy objects to are reported to `diagnostics`
+    /// too: if it's lenient they're a warning and the last one is used.
+    ///
+    /// [`try_lump_index`]: Self::try_lump_index
+    pub(super) fn namespace_indices(
+        self: &Arc<Self>,
+        namespace: Namespace,
+        policy: DuplicatePolicy,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Vec<usize>> {
+        let mut names: Vec<Vec<usize>> = Vec::new();
+        let mut positions: HashMap<LumpName, usize> = HashMap::new();
+
+        for index in self.namespace_members(namespace)? {
+            let name = self.lump_locations[index].name;
+            match positions.get(&name) {
+                Some(&position) => names[position].push(index),
+                None => {
+                    positions.insert(name, names.len());
+                    names.push(vec![index]);
                 }
             }
         }
+
+        names
+            .into_iter()
+            .map(|indices| match self.settle_duplicates(&indices, policy)? {
+                (index, None) => Ok(index),
+                (index, Some(err)) => {
+                    diagnostics.report(err)?;
+                    Ok(index)
+                }
+            })
+            .collect()
+    }
+
+    /// Picks which of several lumps with the same name to use according to `policy`. Returns the
+    /// chosen index, along with an error describing the duplicates if the policy objects to them.
+    fn settle_duplicates(
+        self: &Arc<Self>,
+        indices: &[usize],
+        policy: DuplicatePolicy,
+    ) -> wad::Result<(usize, Option<wad::Error>)> {
+        let last = *indices.last().unwrap();
+        if indices.len() == 1 {
+            return Ok((last, None));
+        }
+
+        match policy {
+            DuplicatePolicy::First => return Ok((indices[0], None)),
+            DuplicatePolicy::Last => return Ok((last, None)),
+            DuplicatePolicy::Error | DuplicatePolicy::LastWithWarning => {}
+        }
+
+        let mut lumps =
+            indices.iter().map(|&index| self.read_lump(index)).collect::<wad::Result<Vec<_>>>()?;
+        lumps.dedup_by(|l1, l2| l1.data() == l2.data());
+
+        if lumps.len() == 1 && lumps[0].has_data() {
+            return Ok((last, None));
+        }
+
+        let desc = format!("found {} times", indices.len());
+        Ok((last, Some(self.lump_error(last, MalformedKind::Duplicate, desc))))
     }
 
-    /// Reads a lump from the raw data, pulling out a slice.
-    fn read_lump(self: &Arc<Self>, index: usize) -> Lump {
+    /// Reads a lump. If the whole file is loaded this pulls out a slice of the raw data, otherwise
+    /// it reads the lump from the underlying reader.
+    fn read_lump(self: &Arc<Self>, index: usize) -> wad::Result<Lump> {
         let location = &self.lump_locations[index];
 
         let file = Arc::clone(self);
-        let name = location.name.clone();
-        let data = self.raw.slice(location.offset..location.offset + location.size);
+        let name = location.name;
+        let data = self
+            .read_data(index)
+            .map_err(|err| wad::Error::Io { path: self.path.clone(), source: err })?;
 
-        Lump::new(file, name, data)
+        Ok(Lump::new(file, index, name, data))
     }
 
-    /// Reads one or more lumps from the raw data, pulling out slices.
-    fn read_lumps(self: &Arc<Self>, indices: Range<usize>, is_named: bool) -> Lumps {
+    /// Reads one or more lumps.
+    fn read_lumps(self: &Arc<Self>, indices: Range<usize>, is_named: bool) -> wad::Result<Lumps> {
         assert!(!indices.is_empty());
-        let lumps = indices.map(|index| self.read_lump(index)).collect();
-        Lumps::new(lumps, is_named)
+        let lumps = indices.map(|index| self.read_lump(index)).collect::<wad::Result<_>>()?;
+        Ok(Lumps::new(lumps, is_named))
+    }
+
+    fn read_data(&self, index: usize) -> io::Result<Bytes> {
+        let LumpLocation { offset, size, .. } = self.lump_locations[index];
+
+        match &self.storage {
+            Storage::Loaded(raw) => Ok(raw.slice(offset..offset + size)),
+
+            Storage::Lazy { reader, cache } => {
+                if let Some(data) = cache[index].get() {
+                    return Ok(data.clone());
+                }
+                if size == 0 {
+                    return Ok(cache[index].get_or_init(Bytes::new).clone());
+                }
+
+                // A panic while reading can't leave the reader in a bad state since every read
+                // starts with a seek. It's safe to ignore poisoning.
+                let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+                let mut data = vec![0; size];
+                reader.seek(SeekFrom::Start(offset as u64))?;
+                reader.read_exact(&mut data)?;
+
+                Ok(cache[index].get_or_init(|| Bytes::from(data)).clone())
+            }
+        }
     }
 
     /// Retrieves all of the lumps in the file.
     ///
     /// An unordered dump of all lumps is rarely useful. This can be useful for debugging, or just
     /// to inspect the contents of a WAD. It's not used by any of the asset loading code.
-    pub fn lumps(self: &Arc<Self>) -> impl Iterator<Item = Lump> + DoubleEndedIterator {
-        self.read_lumps(0..self.lump_indices.len(), false).into_iter()
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file was opened [lazily] and a lump can't be read. Use [`try_lumps`] to handle
+    /// read errors.
+    ///
+    /// [lazily]: Self::load_lazy
+    /// [`try_lumps`]: Self::try_lumps
+    pub fn lumps(self: &Arc<Self>) -> impl DoubleEndedIterator<Item = Lump> {
+        self.try_lumps().map(|lump| lump.unwrap_or_else(|err| panic!("{}", err)))
+    }
+
+    /// Retrieves all of the lumps in the file, reporting read errors instead of panicking.
+    ///
+    /// Lumps are read one at a time as the iterator advances. Reading can only fail if the file was
+    /// opened [lazily].
+    ///
+    /// [lazily]: Self::load_lazy
+    pub fn try_lumps(self: &Arc<Self>) -> impl DoubleEndedIterator<Item = wad::Result<Lump>> {
+        let file = Arc::clone(self);
+        (0..self.lump_locations.len()).map(move |index| file.read_lump(index))
+    }
+
+    /// Checks the file's layout for problems that loading doesn't catch: overlapping lumps, wasted
+    /// space, empty lumps, unbalanced namespace markers, and more. See [`LintKind`] for the full
+    /// list.
+    ///
+    /// Only the lump directory is inspected, so this is fast even for files opened [lazily].
+    ///
+    /// [lazily]: Self::load_lazy
+    /// [`LintKind`]: crate::wad::LintKind
+    pub fn lint(&self) -> LintReport {
+        lint::lint(self)
+    }
+
+    /// Rebuilds the file with its lump data laid out contiguously, reclaiming gaps and storing
+    /// lumps with identical contents only once. Lump names, order, and contents are unchanged,
+    /// except for any lumps dropped according to `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{CompactOptions, WadFile};
+    ///
+    /// let file = WadFile::load("killer.wad")?;
+    /// let compaction = file.compact(CompactOptions { remove_empty: true })?;
+    /// println!("saved {} bytes", compaction.bytes_saved());
+    /// compaction.save("killer-compact.wad")?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Reading the lumps can only fail if the file was opened [lazily].
+    ///
+    /// [lazily]: Self::load_lazy
+    pub fn compact(self: &Arc<Self>, options: CompactOptions) -> wad::Result<Compaction> {
+        compact::compact(self, options)
+    }
+
+    /// Identifies the game this IWAD is for. Returns `None` if it doesn't look like any known game.
+    ///
+    /// The first call hashes the entire file, which takes a moment for larger IWADs. The result is
+    /// cached.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{GameMission, WadFile};
+    ///
+    /// let file = WadFile::load("doom2.wad")?;
+    /// let game = file.game()?.expect("unknown IWAD");
+    /// assert_eq!(game.mission(), GameMission::Doom2);
+    /// println!("{} {:?}", game.mission(), game.version());
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Reading the file can only fail if it was opened [lazily].
+    ///
+    /// [lazily]: Self::load_lazy
+    pub fn game(&self) -> wad::Result<Option<Game>> {
+        if let Some(game) = self.game.get() {
+            return Ok(*game);
+        }
+        let game = game::identify(self)?;
+        Ok(*self.game.get_or_init(|| game))
+    }
+
+    /// The namespace lump `index` is in. Unbalanced markers are ignored.
+    pub(super) fn lump_namespace(&self, index: usize) -> Namespace {
+        let namespaces = self.lump_namespaces.get_or_init(|| {
+            self.namespaces_with(&mut Diagnostics::lenient()).expect("lenient diagnostics failed")
+        });
+        namespaces[index]
+    }
+
+    /// Returns `true` if the file has at least one lump named `name`.
+    pub(super) fn has_lump(&self, name: &str) -> bool {
+        name.to_lump_name().is_some_and(|name| self.lump_indices.contains_key(&name))
+    }
+
+    /// Hashes the entire file with MD5.
+    pub(super) fn md5(&self) -> io::Result<[u8; 16]> {
+        let mut md5 = Md5::new();
+
+        match &self.storage {
+            Storage::Loaded(raw) => md5.update(raw),
+
+            Storage::Lazy { reader, .. } => {
+                let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+                reader.seek(SeekFrom::Start(0))?;
+                let mut buffer = vec![0; 64 * 1024];
+                loop {
+                    let count = reader.read(&mut buffer)?;
+                    if count == 0 {
+                        break;
+                    }
+                    md5.update(&buffer[..count]);
+                }
+            }
+        }
+
+        Ok(md5.finalize().into())
+    }
+
+    /// Reads an arbitrary range of the file, whether or not it belongs to a lump.
+    pub(super) fn read_range(&self, range: Range<usize>) -> io::Result<Bytes> {
+        match &self.storage {
+            Storage::Loaded(raw) => Ok(raw.slice(range)),
+
+            Storage::Lazy { reader, .. } => {
+                let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+                let mut data = vec![0; range.len()];
+                reader.seek(SeekFrom::Start(range.start as u64))?;
+                reader.read_exact(&mut data)?;
+                Ok(Bytes::from(data))
+            }
+        }
+    }
+
+    /// The directory entries, after any lenient fixes.
+    pub(super) fn lump_locations(&self) -> &[LumpLocation] {
+        &self.lump_locations
+    }
+
+    /// The directory's offset in the file, as given in the header.
+    pub(super) fn directory_offset(&self) -> usize {
+        self.directory_offset
+    }
+
+    /// The size of the file in bytes.
+    pub(super) fn file_size(&self) -> usize {
+        self.file_size
     }
 
     /// Creates a [`wad::Error::Malformed`] blaming this file.
-    pub fn error(&self, desc: impl Into<Cow<'static, str>>) -> wad::Error {
-        wad::Error::malformed(&self.path, desc)
+    pub fn error(&self, kind: MalformedKind, desc: impl Into<Cow<'static, str>>) -> wad::Error {
+        wad::Error::malformed(&self.path, kind, desc)
+    }
+
+    /// Creates a [`wad::Error::Malformed`] blaming the lump at `index`, without having to read it.
+    fn lump_error(
+        &self,
+        index: usize,
+        kind: MalformedKind,
+        desc: impl Into<Cow<'static, str>>,
+    ) -> wad::Error {
+        let location = ErrorLocation { lump: self.lump_locations[index].name, index, offset: None };
+        wad::Error::malformed_at(&self.path, kind, location, desc)
     }
 }
 
 impl fmt::Debug for WadFile {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let Self { path, raw, kind, lump_locations, lump_indices } = self;
+        let Self {
+            path,
+            storage,
+            kind,
+            directory_offset,
+            file_size,
+            lump_locations,
+            lump_indices,
+            game,
+            lump_namespaces: _,
+        } = self;
+
+        let storage = match storage {
+            Storage::Loaded(raw) => format!("<{} bytes>", raw.len()),
+            Storage::Lazy { cache, .. } => format!(
+                "<lazy, {} of {} lumps read>",
+                cache.iter().filter(|data| data.get().is_some()).count(),
+                cache.len()
+            ),
+        };
 
         fmt.debug_struct("WadFile")
             .field("path", &path)
-            .field("raw", &format!("<{} bytes>", raw.len()))
+            .field("storage", &storage)
             .field("kind", &kind)
+            .field("directory_offset", &directory_offset)
+            .field("file_size", &file_size)
             .field("lump_locations", &lump_locations)
             .field("lump_indices", &lump_indices)
+            .field("game", &game.get())
             .finish()
     }
 }
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::wad::{self, is_legal_name, Diagnostics, Namespace, WadBuilder, WadFile, WadKind};
+
+/// Lays out a tree of files, such as a [PK3] archive or a [loose directory], as a PWAD.
+///
+/// Files in the root folder and in `sounds/`, `music/`, and `graphics/` become global lumps. Files
+/// in namespace folders like `flats/` and `sprites/`, including their subfolders, are placed
+/// between the namespace's [markers]. WAD files in `maps/` are inlined, with their first lump
+/// renamed after the file so `maps/MAP01.wad` provides `MAP01`. Anything else is ignored.
+///
+/// Lump names come from file names with the extension removed and converted to uppercase.
+/// Files whose names can't be expressed as lump names, say because they're longer than 8
+/// characters, are ignored.
+///
+/// [PK3]: WadFile::load_pk3
+/// [loose directory]: WadFile::load_dir
+/// [markers]: Namespace::markers
+pub(super) struct FolderLayout {
+    path: PathBuf,
+    files: Vec<(String, Namespace, String, Bytes)>,
+    maps: Vec<(String, String, Bytes)>,
+}
+
+impl FolderLayout {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_owned(), files: Vec::new(), maps: Vec::new() }
+    }
+
+    /// Adds a file given its `/`-separated path relative to the root of the tree.
+    pub fn add(&mut self, relative_path: &str, data: Bytes) {
+        let components: Vec<&str> = relative_path.split('/').filter(|c| !c.is_empty()).collect();
+        let (file_name, folders) = match components.split_last() {
+            Some(split) => split,
+            None => return,
+        };
+        let (stem, extension) = file_name.split_once('.').unwrap_or((file_name, ""));
+        let name = stem.to_ascii_uppercase();
+        if !is_legal_name(&name) {
+            return;
+        }
+
+        let namespace = match folders.first() {
+            None => Namespace::Global,
+
+            Some(folder) if folder.eq_ignore_ascii_case("maps") => {
+                if folders.len() == 1 && extension.eq_ignore_ascii_case("wad") {
+                    self.maps.push((relative_path.to_owned(), name, data));
+                }
+                return;
+            }
+
+            Some(folder) => match Namespace::from_folder(folder) {
+                Some(namespace) => namespace,
+                None => return,
+            },
+        };
+
+        self.files.push((relative_path.to_owned(), namespace, name, data));
+    }
+
+    /// Builds the PWAD. Map WADs are loaded with `diagnostics`. If it's lenient, maps that can't
+    /// be loaded at all are reported and left out.
+    pub fn build(mut self, diagnostics: &mut Diagnostics) -> wad::Result<Arc<WadFile>> {
+        // File order in archives and directory listings is arbitrary. Sort by path to make the lump
+        // order predictable.
+        self.files.sort_by(|a, b| a.0.cmp(&b.0));
+        self.maps.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+
+        for namespace in Namespace::ALL {
+            let mut lumps = self.files.iter().filter(|(_, ns, _, _)| *ns == namespace).peekable();
+            if lumps.peek().is_none() {
+                continue;
+            }
+
+            let markers = namespace.primary_markers();
+            if let Some((start, _)) = markers {
+                builder.marker(start);
+            }
+            for (_, _, name, data) in lumps {
+                builder.lump(name, data.clone());
+            }
+            if let Some((_, end)) = markers {
+                builder.marker(end);
+            }
+        }
+
+        for (relative_path, name, data) in self.maps {
+            let file =
+                match WadFile::load_raw_with(self.path.join(relative_path), data, diagnostics) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        diagnostics.report(err)?;
+                        continue;
+                    }
+                };
+
+            for (index, lump) in file.lumps().enumerate() {
+                if index == 0 {
+                    builder.lump(&name, lump.data().to_vec());
+                } else {
+                    builder.add_lump(&lump);
+                }
+            }
+        }
+
+        builder.build_file(&self.path)
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use crate::wad::{self, LumpName, WadFile};
+
+/// The game an IWAD is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameMission {
+    /// DOOM, including The Ultimate DOOM and Freedoom: Phase 1.
+    Doom,
+
+    /// DOOM II: Hell on Earth, including Freedoom: Phase 2 and FreeDM.
+    Doom2,
+
+    /// Final DOOM: TNT: Evilution.
+    Tnt,
+
+    /// Final DOOM: The Plutonia Experiment.
+    Plutonia,
+
+    /// Chex Quest.
+    Chex,
+
+    /// Heretic.
+    Heretic,
+
+    /// Hexen.
+    Hexen,
+}
+
+impl GameMission {
+    /// Returns `true` if maps are named `ExMy`, or `false` if they're named `MAPxx`.
+    pub fn is_episodic(self) -> bool {
+        match self {
+            Self::Doom | Self::Chex | Self::Heretic => true,
+            Self::Doom2 | Self::Tnt | Self::Plutonia | Self::Hexen => false,
+        }
+    }
+
+    /// The name of a map's marker lump, such as `E1M1` or `MAP01`. `episode` is ignored if the
+    /// game isn't [episodic]. Returns `None` if `episode` or `map` are too large to fit in a lump
+    /// name.
+    ///
+    /// [episodic]: Self::is_episodic
+    pub fn map_name(self, episode: u32, map: u32) -> Option<LumpName> {
+        let name = if self.is_episodic() {
+            format!("E{}M{}", episode, map)
+        } else {
+            format!("MAP{:02}", map)
+        };
+        LumpName::new(&name).ok()
+    }
+}
+
+impl fmt::Display for GameMission {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let title = match self {
+            Self::Doom => "DOOM",
+            Self::Doom2 => "DOOM II",
+            Self::Tnt => "TNT: Evilution",
+            Self::Plutonia => "The Plutonia Experiment",
+            Self::Chex => "Chex Quest",
+            Self::Heretic => "Heretic",
+            Self::Hexen => "Hexen",
+        };
+        write!(fmt, "{}", title)
+    }
+}
+
+/// How much of a game an IWAD contains, determined by which maps are present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// The first episode only.
+    Shareware,
+
+    /// Three episodes.
+    Registered,
+
+    /// Four or more episodes: The Ultimate DOOM, or Heretic: Shadow of the Serpent Riders.
+    Retail,
+
+    /// A single run of `MAPxx` maps, as in DOOM II and its successors.
+    Commercial,
+}
+
+/// A specific release of an IWAD, recognized by its MD5 hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameVersion {
+    /// Hexen 1.1.
+    V1_1,
+
+    /// Heretic 1.3.
+    V1_3,
+
+    /// DOOM or DOOM II 1.666.
+    V1_666,
+
+    /// DOOM or DOOM II 1.9.
+    V1_9,
+
+    /// The Ultimate DOOM.
+    Ultimate,
+
+    /// Final DOOM.
+    Final,
+
+    /// Chex Quest.
+    Chex,
+}
+
+/// A recognized IWAD. See [`WadFile::game`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Game {
+    mission: GameMission,
+    mode: GameMode,
+    version: Option<GameVersion>,
+    freedoom: bool,
+}
+
+impl Game {
+    /// The game.
+    pub fn mission(&self) -> GameMission {
+        self.mission
+    }
+
+    /// How much of the game is present.
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// The exact release, if the file is a known, unmodified IWAD.
+    pub fn version(&self) -> Option<GameVersion> {
+        self.version
+    }
+
+    /// Returns `true` if the IWAD is one of the Freedoom project's replacement IWADs.
+    pub fn is_freedoom(&self) -> bool {
+        self.freedoom
+    }
+}
+
+struct KnownIwad {
+    md5: &'static str,
+    mission: GameMission,
+    version: GameVersion,
+}
+
+const fn known(md5: &'static str, mission: GameMission, version: GameVersion) -> KnownIwad {
+    KnownIwad { md5, mission, version }
+}
+
+/// Releases that can be recognized by their MD5 hash.
+const KNOWN_IWADS: [KnownIwad; 11] = [
+    known("f0cefca49926d00903cf57551d901abe", GameMission::Doom, GameVersion::V1_9),
+    known("54978d12de87f162b9bcc011676cb3c0", GameMission::Doom, GameVersion::V1_666),
+    known("1cd63c5ddff1bf8ce844237f580e9cf3", GameMission::Doom, GameVersion::V1_9),
+    known("c4fe9fd920207691a9f493668e0a2083", GameMission::Doom, GameVersion::Ultimate),
+    known("30e3c2d0350b67bfbf47271970b74b2f", GameMission::Doom2, GameVersion::V1_666),
+    known("25e1459ca71d321525f84628f45ca8cd", GameMission::Doom2, GameVersion::V1_9),
+    known("4e158d9953c79ccf97bd0663244cc6b6", GameMission::Tnt, GameVersion::Final),
+    known("75c8cf89566741fa9d22447604053bd7", GameMission::Plutonia, GameVersion::Final),
+    known("25485721882b050afa96a56e5758dd52", GameMission::Chex, GameVersion::Chex),
+    known("66d686b1ed6d35ff103f15dbd30e0341", GameMission::Heretic, GameVersion::V1_3),
+    known("abb033caf81e26f12a2103e1fa25453f", GameMission::Hexen, GameVersion::V1_1),
+];
+
+/// Identifies the game `file` is for. Known releases are recognized by their hash. Anything else
+/// is identified by the lumps it contains and, for games that can't be told apart that way, by
+/// its file name as source ports do.
+pub(super) fn identify(file: &WadFile) -> wad::Result<Option<Game>> {
+    let has = |name: &str| file.has_lump(name);
+
+    let md5 =
+        file.md5().map_err(|err| wad::Error::Io { path: file.path().to_owned(), source: err })?;
+    let md5: String = md5.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let known = KNOWN_IWADS.iter().find(|iwad| iwad.md5 == md5);
+
+    let stem = file
+        .path()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let mission = match known {
+        Some(known) => known.mission,
+        // Heretic and Hexen have a `TITLE` lump where DOOM has `TITLEPIC`.
+        None if has("TITLE") && has("MAP01") => GameMission::Hexen,
+        None if has("TITLE") && has("E1M1") => GameMission::Heretic,
+        None if has("MAP01") => match stem.as_str() {
+            "tnt" => GameMission::Tnt,
+            "plutonia" => GameMission::Plutonia,
+            _ => GameMission::Doom2,
+        },
+        None if has("E1M1") => match stem.as_str() {
+            "chex" => GameMission::Chex,
+            _ => GameMission::Doom,
+        },
+        None => return Ok(None),
+    };
+
+    let mode = if !mission.is_episodic() {
+        GameMode::Commercial
+    } else if has("E4M1") {
+        GameMode::Retail
+    } else if has("E2M1") {
+        GameMode::Registered
+    } else {
+        GameMode::Shareware
+    };
+
+    Ok(Some(Game {
+        mission,
+        mode,
+        version: known.map(|known| known.version),
+        freedoom: has("FREEDOOM") || has("FREEDM"),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::wad::{WadBuilder, WadFile, WadKind};
+
+    fn identify(path: &str, lumps: &[&str]) -> Option<Game> {
+        let mut builder = WadBuilder::new(WadKind::Iwad);
+        for &lump in lumps {
+            builder.lump(lump, vec![0; 10]);
+        }
+        builder.build_file(path).unwrap().game().unwrap()
+    }
+
+    #[test]
+    fn missions() {
+        let game = identify("doom1.wad", &["PLAYPAL", "E1M1"]).unwrap();
+        assert_eq!(game.mission(), GameMission::Doom);
+        assert_eq!(game.mode(), GameMode::Shareware);
+        assert_eq!(game.version(), None);
+        assert!(!game.is_freedoom());
+
+        let game = identify("doom.wad", &["E1M1", "E2M1", "E3M1"]).unwrap();
+        assert_eq!((game.mission(), game.mode()), (GameMission::Doom, GameMode::Registered));
+
+        let game = identify("freedoom1.wad", &["E1M1", "E4M1", "FREEDOOM"]).unwrap();
+        assert_eq!((game.mission(), game.mode()), (GameMission::Doom, GameMode::Retail));
+        assert!(game.is_freedoom());
+
+        let game = identify("doom2.wad", &["MAP01"]).unwrap();
+        assert_eq!((game.mission(), game.mode()), (GameMission::Doom2, GameMode::Commercial));
+
+        let game = identify("/games/TNT.WAD", &["MAP01"]).unwrap();
+        assert_eq!(game.mission(), GameMission::Tnt);
+
+        let game = identify("heretic.wad", &["TITLE", "E1M1", "E2M1"]).unwrap();
+        assert_eq!((game.mission(), game.mode()), (GameMission::Heretic, GameMode::Registered));
+
+        let game = identify("hexen.wad", &["TITLE", "MAP01"]).unwrap();
+        assert_eq!((game.mission(), game.mode()), (GameMission::Hexen, GameMode::Commercial));
+
+        assert_eq!(identify("empty.wad", &["PLAYPAL"]), None);
+    }
+
+    #[test]
+    fn md5() {
+        let raw = WadBuilder::new(WadKind::Iwad).lump("PLAYPAL", vec![0; 10]).build();
+        let hex = |file: Arc<WadFile>| -> String {
+            file.md5().unwrap().iter().map(|byte| format!("{:02x}", byte)).collect()
+        };
+
+        let loaded = WadFile::load_raw("test.wad", raw.clone()).unwrap();
+        let lazy = WadFile::load_reader_lazy("test.wad", io::Cursor::new(raw.to_vec())).unwrap();
+        assert_eq!(hex(loaded), "92c7b837e7378194f2f88390192a6b36");
+        assert_eq!(hex(lazy), "92c7b837e7378194f2f88390192a6b36");
+    }
+
+    #[test]
+    fn map_name() {
+        assert_eq!(GameMission::Doom.map_name(2, 7).unwrap(), "E2M7");
+        assert_eq!(GameMission::Plutonia.map_name(1, 7).unwrap(), "MAP07");
+        assert_eq!(GameMission::Hexen.map_name(1, 40).unwrap(), "MAP40");
+        assert_eq!(GameMission::Doom2.map_name(1, 99999).unwrap(), "MAP99999");
+        assert_eq!(GameMission::Doom2.map_name(1, 1000000), None);
+        assert_eq!(GameMission::Doom.map_name(12345, 1).unwrap(), "E12345M1");
+        assert_eq!(GameMission::Doom.map_name(123456, 1), None);
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::wad::resolved::MAP_LUMPS;
+use crate::wad::{self, Diagnostics, ErrorLocation, Namespace, WadFile};
+
+/// Lumps that belong in the global namespace. Finding one inside a namespace block usually means
+/// a marker is missing.
+const GLOBAL_LUMPS: [&str; 10] = [
+    "PLAYPAL", "COLORMAP", "ENDOOM", "PNAMES", "TEXTURE1", "TEXTURE2", "GENMIDI", "DMXGUS",
+    "DMXGUSC", "DEHACKED",
+];
+
+/// The size of the WAD header.
+const HEADER_SIZE: usize = 12;
+
+/// The results of [`WadFile::lint`].
+///
+/// The [`Display`] impl prints one line per finding, prefixed by the file path, which makes it easy
+/// to run in a release pipeline:
+///
+/// ```no_run
+/// use dusty_room::wad::WadFile;
+///
+/// let report = WadFile::load("killer.wad")?.lint();
+/// if !report.is_clean() {
+///     eprint!("{}", report);
+///     std::process::exit(1);
+/// }
+/// #
+/// # Ok::<(), dusty_room::wad::Error>(())
+/// ```
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone, Debug)]
+pub struct LintReport {
+    path: PathBuf,
+    findings: Vec<Finding>,
+    wasted_bytes: usize,
+}
+
+impl LintReport {
+    /// The path of the file that was checked.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The problems found. Problems with the file as a whole come first, followed by problems with
+    /// individual lumps in directory order.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Returns `true` if no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The total number of bytes in [gaps] that don't belong to any lump.
+    ///
+    /// [gaps]: LintKind::Gap
+    pub fn wasted_bytes(&self) -> usize {
+        self.wasted_bytes
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(fmt, "{}: {}", self.path.display(), finding)?;
+        }
+        Ok(())
+    }
+}
+
+/// A problem found by [`WadFile::lint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// What sort of problem it is.
+    pub kind: LintKind,
+
+    /// The lump with the problem, if it can be pinned down to one.
+    pub location: Option<ErrorLocation>,
+
+    /// A description of the problem.
+    pub desc: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(fmt, "{}: {}", location.lump, self.desc),
+            None => write!(fmt, "{}", self.desc),
+        }
+    }
+}
+
+/// Classifies lint [`Finding`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A lump's data overlaps another lump or the lump directory. Lumps that share exactly the same
+    /// data, a space-saving trick used by some WAD tools, are fine.
+    Overlap,
+
+    /// Bytes that don't belong to the header, the lump directory, or any lump. Editors that append
+    /// changes instead of rewriting the file leave these behind.
+    Gap,
+
+    /// A lump with no data that isn't a namespace or map marker.
+    EmptyLump,
+
+    /// A `*_START` or `*_END` marker without its partner, or one inside a different namespace.
+    UnbalancedMarkers,
+
+    /// A lump in the wrong namespace, like `PLAYPAL` inside a flats block, or map data that isn't
+    /// part of a map.
+    WrongNamespace,
+
+    /// A lump whose data starts inside the 12-byte header.
+    PointsAtHeader,
+}
+
+/// Which part of the file a byte range belongs to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Owner {
+    Header,
+    Directory,
+    Lump(usize),
+}
+
+pub(super) fn lint(file: &WadFile) -> LintReport {
+    let mut findings = Vec::new();
+    let wasted_bytes = check_layout(file, &mut findings);
+    check_namespaces(file, &mut findings);
+
+    findings.sort_by_key(|finding| finding.location.as_ref().map(|location| location.index));
+
+    LintReport { path: file.path().to_owned(), findings, wasted_bytes }
+}
+
+fn finding(file: &WadFile, kind: LintKind, index: Option<usize>, desc: String) -> Finding {
+    let location = index.map(|index| ErrorLocation {
+        lump: file.lump_locations()[index].name,
+        index,
+        offset: None,
+    });
+    Finding { kind, location, desc }
+}
+
+/// Looks for overlaps and gaps between the header, the directory, and the lumps. Returns the number
+/// of wasted bytes.
+fn check_layout(file: &WadFile, findings: &mut Vec<Finding>) -> usize {
+    let locations = file.lump_locations();
+    let file_size = file.file_size();
+
+    let directory_start = file.directory_offset().min(file_size);
+    let directory_end = file.directory_offset().saturating_add(16 * locations.len()).min(file_size);
+    let mut ranges = vec![
+        (0..HEADER_SIZE.min(file_size), Owner::Header),
+        (directory_start..directory_end, Owner::Directory),
+    ];
+
+    for (index, location) in locations.iter().enumerate() {
+        if location.size == 0 {
+            continue;
+        }
+        if location.offset < HEADER_SIZE {
+            let desc = format!("data at offset {} is inside the header", location.offset);
+            findings.push(finding(file, LintKind::PointsAtHeader, Some(index), desc));
+        }
+        ranges.push((location.offset..location.offset + location.size, Owner::Lump(index)));
+    }
+
+    ranges.retain(|(range, _)| !range.is_empty());
+    ranges.sort_by_key(|(range, _)| (range.start, range.end));
+
+    let mut wasted_bytes = 0;
+    let mut gap = |range: Range<usize>, findings: &mut Vec<Finding>| {
+        wasted_bytes += range.len();
+        let desc = format!("{} unused bytes at offset {}", range.len(), range.start);
+        findings.push(finding(file, LintKind::Gap, None, desc));
+    };
+
+    // The range that reaches furthest into the file so far.
+    let mut furthest: Option<(Range<usize>, Owner)> = None;
+
+    for (range, owner) in ranges {
+        let covered = furthest.as_ref().map_or(0, |(furthest, _)| furthest.end);
+
+        if range.start > covered {
+            gap(covered..range.start, findings);
+        } else if let Some((other_range, other)) = &furthest {
+            if range.start < covered && range != *other_range {
+                match (*other, owner) {
+                    // Reported as `PointsAtHeader` instead.
+                    (Owner::Header, _) | (_, Owner::Header) => {}
+
+                    (Owner::Lump(other), Owner::Lump(index)) => {
+                        let other_name = file.lump_locations()[other].name;
+                        let desc = format!("data overlaps {} (#{})", other_name, other);
+                        findings.push(finding(file, LintKind::Overlap, Some(index), desc));
+                    }
+
+                    (Owner::Directory, Owner::Lump(index))
+                    | (Owner::Lump(index), Owner::Directory) => {
+                        let desc = "data overlaps the lump directory".to_owned();
+                        findings.push(finding(file, LintKind::Overlap, Some(index), desc));
+                    }
+
+                    (Owner::Directory, Owner::Directory) => unreachable!(),
+                }
+            }
+        }
+
+        if range.end > covered {
+            furthest = Some((range, owner));
+        }
+    }
+
+    let covered = furthest.map_or(0, |(furthest, _)| furthest.end);
+    if file_size > covered {
+        gap(covered..file_size, findings);
+    }
+
+    wasted_bytes
+}
+
+/// Checks namespace markers, empty lumps, and lumps in the wrong namespace.
+fn check_namespaces(file: &WadFile, findings: &mut Vec<Finding>) {
+    let locations = file.lump_locations();
+
+    let mut diagnostics = Diagnostics::lenient();
+    let namespaces = file.namespaces_with(&mut diagnostics).expect("lenient namespace scan failed");
+
+    for warning in diagnostics.into_warnings() {
+        if let wad::Error::Malformed { location, desc, .. } = warning {
+            let kind = LintKind::UnbalancedMarkers;
+            findings.push(Finding { kind, location, desc: desc.into_owned() });
+        }
+    }
+
+    let mut in_map = false;
+
+    for (index, (location, &namespace)) in locations.iter().zip(&namespaces).enumerate() {
+        let name = location.name;
+        let is_map_lump = MAP_LUMPS.iter().any(|&map_lump| name == map_lump);
+        let is_namespace_marker =
+            Namespace::from_marker(name).is_some() || Namespace::is_sub_marker(name);
+        let is_map_marker = namespace == Namespace::Global
+            && !is_namespace_marker
+            && locations.get(index + 1).is_some_and(|next| next.name == "THINGS");
+
+        if is_map_lump && namespace != Namespace::Global {
+            let desc = format!("map data inside the {} namespace", namespace);
+            findings.push(finding(file, LintKind::WrongNamespace, Some(index), desc));
+        } else if is_map_lump && !in_map {
+            let desc = "map data outside of a map".to_owned();
+            findings.push(finding(file, LintKind::WrongNamespace, Some(index), desc));
+        } else if namespace != Namespace::Global && GLOBAL_LUMPS.iter().any(|&g| name == g) {
+            let desc = format!("global lump inside the {} namespace", namespace);
+            findings.push(finding(file, LintKind::WrongNamespace, Some(index), desc));
+        }
+
+        if location.size == 0 && !is_namespace_marker && !is_map_marker {
+            let desc = "empty lump".to_owned();
+            findings.push(finding(file, LintKind::EmptyLump, Some(index), desc));
+        }
+
+        in_map = is_map_marker || (in_map && is_map_lump);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::wad::{LumpName, WadBuilder, WadKind};
+
+    #[test]
+    fn clean() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("PLAYPAL", vec![0; 768]);
+        builder.marker("F_START").lump("FLAT1", vec![1; 4096]).marker("F_END");
+        builder.map("MAP01", [("THINGS", vec![2; 10]), ("LINEDEFS", vec![3; 14])]);
+
+        let report = builder.build_file("clean.wad")?.lint();
+        assert!(report.is_clean(), "{}", report);
+        assert_eq!(report.wasted_bytes(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn messy() -> wad::Result<()> {
+        let entries: [(&str, u32, u32); 11] = [
+            ("A", 12, 10),
+            ("B", 30, 10),
+            ("C", 35, 10),
+            ("F_START", 0, 0),
+            ("PLAYPAL", 45, 10),
+            ("F_END", 0, 0),
+            ("SHARED", 45, 10),
+            ("EMPTY", 0, 0),
+            ("HEADER", 4, 4),
+            ("S_END", 0, 0),
+            ("THINGS", 12, 10),
+        ];
+
+        let mut raw = b"PWAD".to_vec();
+        raw.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        raw.extend_from_slice(&55u32.to_le_bytes());
+        raw.resize(55, 0xff);
+        for (name, offset, size) in entries {
+            raw.extend_from_slice(&offset.to_le_bytes());
+            raw.extend_from_slice(&size.to_le_bytes());
+            raw.extend_from_slice(&LumpName::new(name).unwrap().to_raw());
+        }
+        raw.extend_from_slice(b"junk");
+
+        let report = WadFile::load_raw("messy.wad", Bytes::from(raw))?.lint();
+        let findings: Vec<_> = report
+            .findings()
+            .iter()
+            .map(|finding| (finding.kind, finding.location.as_ref().map(|l| l.index)))
+            .collect();
+
+        assert_eq!(
+            findings,
+            [
+                (LintKind::Gap, None),
+                (LintKind::Gap, None),
+                (LintKind::Overlap, Some(2)),
+                (LintKind::WrongNamespace, Some(4)),
+                (LintKind::EmptyLump, Some(7)),
+                (LintKind::PointsAtHeader, Some(8)),
+                (LintKind::UnbalancedMarkers, Some(9)),
+                (LintKind::WrongNamespace, Some(10)),
+            ]
+        );
+        assert_eq!(report.wasted_bytes(), 12);
+        assert_eq!(
+            report.to_string().lines().take(3).collect::<Vec<_>>(),
+            [
+                "messy.wad: 8 unused bytes at offset 22",
+                "messy.wad: 4 unused bytes at offset 231",
+                "messy.wad: C: data overlaps B (#1)",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::wad::{self, Game, GameMission, Wad, WadFile, WadKind};
+
+/// IWAD file names and the games they're expected to hold, most preferred first.
+const IWAD_NAMES: [(&str, GameMission); 12] = [
+    ("doom2.wad", GameMission::Doom2),
+    ("plutonia.wad", GameMission::Plutonia),
+    ("tnt.wad", GameMission::Tnt),
+    ("doom.wad", GameMission::Doom),
+    ("doom1.wad", GameMission::Doom),
+    ("chex.wad", GameMission::Chex),
+    ("heretic.wad", GameMission::Heretic),
+    ("heretic1.wad", GameMission::Heretic),
+    ("hexen.wad", GameMission::Hexen),
+    ("freedoom2.wad", GameMission::Doom2),
+    ("freedoom1.wad", GameMission::Doom),
+    ("freedm.wad", GameMission::Doom2),
+];
+
+/// Searches the usual places for IWADs.
+///
+/// The [standard search path] checks, in order:
+///
+/// 1. The current directory.
+/// 2. The directory named by the `DOOMWADDIR` environment variable.
+/// 3. Each directory in the `DOOMWADPATH` environment variable, separated by `:` (`;` on
+///    Windows).
+/// 4. Conventional install locations such as `/usr/share/games/doom`.
+///
+/// File names are matched case-insensitively against the names the games ship with, such as
+/// `doom2.wad`, `tnt.wad`, and `freedoom1.wad`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dusty_room::wad::IwadLocator;
+///
+/// for candidate in IwadLocator::new().candidates() {
+///     println!("{}: {:?}", candidate.path().display(), candidate.game());
+/// }
+/// ```
+///
+/// [standard search path]: Self::new
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct IwadLocator {
+    dirs: Vec<PathBuf>,
+}
+
+/// An IWAD found by an [`IwadLocator`].
+#[derive(Clone, Debug)]
+pub struct IwadCandidate {
+    path: PathBuf,
+    game: Option<Game>,
+}
+
+impl IwadCandidate {
+    /// Where the IWAD is.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The game it's for, if [identified].
+    ///
+    /// [identified]: WadFile::game
+    pub fn game(&self) -> Option<Game> {
+        self.game
+    }
+}
+
+impl IwadLocator {
+    /// Creates a locator with the standard search path.
+    pub fn new() -> Self {
+        Self::from_vars(env::var_os("DOOMWADDIR"), env::var_os("DOOMWADPATH"))
+    }
+
+    fn from_vars(doomwaddir: Option<OsString>, doomwadpath: Option<OsString>) -> Self {
+        let mut locator = Self::empty();
+        locator.dir(".");
+        if let Some(dir) = doomwaddir {
+            locator.dir(dir);
+        }
+        if let Some(path) = doomwadpath {
+            for dir in env::split_paths(&path) {
+                locator.dir(dir);
+            }
+        }
+        for dir in standard_dirs() {
+            locator.dir(dir);
+        }
+        locator
+    }
+
+    /// Creates a locator with an empty search path.
+    pub fn empty() -> Self {
+        Self { dirs: Vec::new() }
+    }
+
+    /// Appends a directory to the search path. Empty paths and repeats are ignored.
+    pub fn dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        let dir = dir.into();
+        if !dir.as_os_str().is_empty() && !self.dirs.contains(&dir) {
+            self.dirs.push(dir);
+        }
+        self
+    }
+
+    /// The directories that are searched, in order.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Lists the IWADs in the search path. Directories are checked in order, and within each
+    /// directory more popular games come first. Files that aren't IWADs or can't be read are
+    /// skipped.
+    ///
+    /// Each IWAD is hashed to [identify] it, which takes a moment.
+    ///
+    /// [identify]: WadFile::game
+    pub fn candidates(&self) -> Vec<IwadCandidate> {
+        self.iwad_paths()
+            .into_iter()
+            .filter_map(|path| match WadFile::load_lazy(&path) {
+                Ok(file) if file.kind() == WadKind::Iwad => {
+                    let game = file.game().ok().flatten();
+                    Some(IwadCandidate { path, game })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lists the files in the search path with IWAD names, in the same order as [`candidates`],
+    /// without opening them.
+    ///
+    /// [`candidates`]: Self::candidates
+    fn iwad_paths(&self) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut paths = Vec::new();
+
+        for dir in &self.dirs {
+            let mut matches: Vec<(usize, PathBuf)> = match fs::read_dir(dir) {
+                Ok(entries) => entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+                        let rank = IWAD_NAMES.iter().position(|&(iwad, _)| iwad == name)?;
+                        Some((rank, entry.path()))
+                    })
+                    .collect(),
+                Err(_) => continue,
+            };
+            matches.sort();
+
+            for (_, path) in matches {
+                // The same directory can appear in the path more than once under different names.
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if seen.insert(canonical) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Looks for a file named `name` in the search path, ignoring case. Unlike [`candidates`] it
+    /// doesn't need to be a known IWAD name.
+    ///
+    /// [`candidates`]: Self::candidates
+    pub fn find_file(&self, name: impl AsRef<Path>) -> Option<PathBuf> {
+        let name = name.as_ref().to_string_lossy().to_ascii_lowercase();
+
+        self.dirs.iter().find_map(|dir| {
+            fs::read_dir(dir).ok()?.flatten().find_map(|entry| {
+                let matches = entry.file_name().to_string_lossy().to_ascii_lowercase() == name;
+                matches.then(|| entry.path())
+            })
+        })
+    }
+
+    /// Finds an IWAD for `mission`. The commercial IWADs are preferred over Freedoom.
+    pub fn find(&self, mission: GameMission) -> Option<IwadCandidate> {
+        let mut candidates: Vec<_> = self
+            .candidates()
+            .into_iter()
+            .filter(|candidate| candidate.game.is_some_and(|game| game.mission() == mission))
+            .collect();
+        candidates.sort_by_key(|candidate| candidate.game.is_some_and(|game| game.is_freedoom()));
+        candidates.into_iter().next()
+    }
+
+    /// Finds and loads an IWAD for `mission`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with an [I/O error] if no IWAD is found.
+    ///
+    /// [I/O error]: wad::Error::Io
+    pub fn load(&self, mission: GameMission) -> wad::Result<Wad> {
+        match self.find(mission) {
+            Some(candidate) => Wad::load(candidate.path),
+            None => {
+                let name = IWAD_NAMES.iter().find(|&&(_, m)| m == mission).unwrap().0;
+                Err(wad::Error::Io {
+                    path: PathBuf::from(name),
+                    source: io::Error::new(io::ErrorKind::NotFound, "no IWAD found in search path"),
+                })
+            }
+        }
+    }
+}
+
+impl Default for IwadLocator {
+    /// Same as [`IwadLocator::new`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Conventional install locations.
+fn standard_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if cfg!(unix) {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+        if let Some(data_home) = data_home {
+            dirs.push(data_home.join("games/doom"));
+        }
+        for dir in [
+            "/usr/local/share/games/doom",
+            "/usr/share/games/doom",
+            "/usr/local/share/doom",
+            "/usr/share/doom",
+        ] {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+
+    if cfg!(windows) {
+        for dir in [r"C:\DOOM2", r"C:\DOOM", r"C:\ULTDOOM", r"C:\TNT", r"C:\PLUTONIA"] {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::WadBuilder;
+
+    #[test]
+    fn search_path() {
+        let path = env::join_paths(["/wads/a", "/wads/b"]).unwrap();
+        let locator = IwadLocator::from_vars(Some("/wads/b".into()), Some(path));
+
+        assert_eq!(
+            locator.dirs()[..3],
+            [PathBuf::from("."), PathBuf::from("/wads/b"), PathBuf::from("/wads/a")]
+        );
+    }
+
+    #[test]
+    fn find() -> wad::Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let mut doom2 = WadBuilder::new(WadKind::Iwad);
+        doom2.lump("PLAYPAL", vec![0; 768]).marker("MAP01");
+        doom2.save(root.join("DOOM2.WAD"))?;
+        doom2.lump("FREEDOOM", vec![0; 10]).save(root.join("freedoom2.wad"))?;
+        WadBuilder::new(WadKind::Pwad).marker("MAP01").save(root.join("tnt.wad"))?;
+        fs::write(root.join("doom.wad"), "not a WAD").unwrap();
+
+        let mut locator = IwadLocator::empty();
+        locator.dir(root).dir(root.join(".")).dir(root.join("missing"));
+
+        let candidates = locator.candidates();
+        assert_eq!(
+            candidates.iter().map(|candidate| candidate.path()).collect::<Vec<_>>(),
+            [root.join("DOOM2.WAD"), root.join("freedoom2.wad")]
+        );
+        assert!(candidates[1].game().unwrap().is_freedoom());
+
+        let found = locator.find(GameMission::Doom2).unwrap();
+        assert_eq!(found.path(), root.join("DOOM2.WAD"));
+        assert_eq!(found.game().unwrap().mission(), GameMission::Doom2);
+
+        assert_eq!(locator.find_file("Doom.Wad"), Some(root.join("doom.wad")));
+        assert!(locator.find(GameMission::Tnt).is_none());
+        assert_matches!(locator.load(GameMission::Tnt), Err(wad::Error::Io { .. }));
+        assert!(locator.load(GameMission::Doom2)?.lump("PLAYPAL").is_ok());
+
+        Ok(())
+    }
+}
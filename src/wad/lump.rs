@@ -5,7 +5,7 @@ use std::{fmt, slice, vec};
 
 use bytes::Bytes;
 
-use crate::wad::{self, Cursor, WadFile};
+use crate::wad::{self, Cursor, ErrorLocation, LumpName, MalformedKind, Namespace, WadFile};
 
 /// A block of one or more [`Lump`]s from a [`Wad`] or [`WadFile`].
 ///
@@ -23,10 +23,10 @@ impl Lumps {
         assert!(!lumps.is_empty());
 
         if is_named {
-            let name = lumps[0].name.clone();
+            let name = lumps[0].name;
 
             for lump in lumps.iter_mut() {
-                *lump = lump.from_block(name.clone());
+                *lump = lump.with_block(name);
             }
         }
 
@@ -49,9 +49,9 @@ impl Lumps {
         self.0.last().unwrap()
     }
 
-    /// Creates a [`wad::Error::Malformed`] blaming this block.
-    pub fn error(&self, desc: impl Into<Cow<'static, str>>) -> wad::Error {
-        self.first().file.error(desc)
+    /// Creates a [`wad::Error::Malformed`] blaming this block. The error points at the first lump.
+    pub fn error(&self, kind: MalformedKind, desc: impl Into<Cow<'static, str>>) -> wad::Error {
+        self.first().error(kind, desc)
     }
 }
 
@@ -102,18 +102,19 @@ impl<'a> IntoIterator for &'a mut Lumps {
 #[derive(Clone)]
 pub struct Lump {
     file: Arc<WadFile>,
-    block: Option<String>,
-    name: String,
+    index: usize,
+    block: Option<LumpName>,
+    name: LumpName,
     data: Bytes,
 }
 
 impl Lump {
     /// Creates a lump pointing at a slice of data from a `WadFile`.
-    pub(super) fn new(file: Arc<WadFile>, name: String, data: Bytes) -> Self {
-        Self { file, block: None, name, data }
+    pub(super) fn new(file: Arc<WadFile>, index: usize, name: LumpName, data: Bytes) -> Self {
+        Self { file, index, block: None, name, data }
     }
 
-    pub(super) fn from_block(&self, block: String) -> Self {
+    pub(super) fn with_block(&self, block: LumpName) -> Self {
         Self { block: Some(block), ..self.clone() }
     }
 
@@ -122,9 +123,20 @@ impl Lump {
         &self.file
     }
 
+    /// The lump's index in its file's lump directory.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
     /// The lump name, for example `VERTEXES` or `THINGS`.
-    pub fn name(&self) -> &str {
-        &self.name
+    pub fn name(&self) -> LumpName {
+        self.name
+    }
+
+    /// The namespace the lump is in, such as [`Namespace::Flats`] if it's between `F_START` and
+    /// `F_END`. Unbalanced markers are ignored.
+    pub fn namespace(&self) -> Namespace {
+        self.file.lump_namespace(self.index)
     }
 
     /// The lump data, a binary blob.
@@ -132,6 +144,11 @@ impl Lump {
         &self.data
     }
 
+    /// The lump data as a cheaply cloneable [`Bytes`] handle.
+    pub(super) fn bytes(&self) -> &Bytes {
+        &self.data
+    }
+
     /// Returns a cursor that can be used to parse the lump data.
     pub fn cursor(&self) -> Cursor<'_> {
         Cursor::new(self, self.data.clone())
@@ -163,19 +180,39 @@ impl Lump {
         if self.name == name {
             Ok(self)
         } else {
-            Err(self.error(format!("{} missing", name)))
+            Err(self.error(MalformedKind::Missing, format!("{} missing", name)))
         }
     }
 
     /// Creates a [`wad::Error::Malformed`] blaming this lump.
-    pub fn error(&self, desc: impl Into<Cow<'static, str>>) -> wad::Error {
-        self.file.error(format!("{}: {}", self.name(), desc.into()))
+    pub fn error(&self, kind: MalformedKind, desc: impl Into<Cow<'static, str>>) -> wad::Error {
+        self.error_location(kind, None, desc)
+    }
+
+    /// Creates a [`wad::Error::Malformed`] blaming the data at `offset` bytes into this lump.
+    pub fn error_at(
+        &self,
+        offset: usize,
+        kind: MalformedKind,
+        desc: impl Into<Cow<'static, str>>,
+    ) -> wad::Error {
+        self.error_location(kind, Some(offset), desc)
+    }
+
+    fn error_location(
+        &self,
+        kind: MalformedKind,
+        offset: Option<usize>,
+        desc: impl Into<Cow<'static, str>>,
+    ) -> wad::Error {
+        let location = ErrorLocation { lump: self.name, index: self.index, offset };
+        wad::Error::malformed_at(self.file.path(), kind, location, desc)
     }
 }
 
 impl fmt::Debug for Lump {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let Self { file, block, name, data } = self;
+        let Self { file, index: _, block, name, data } = self;
 
         if let Some(block) = block {
             write!(fmt, "{} ", block)?;
@@ -17,20 +17,45 @@
 //! # Ok::<(), dusty_room::wad::Error>(())
 //! ```
 
+pub use builder::*;
+pub use classify::*;
+pub use compact::*;
 pub use cursor::*;
+pub use diagnostics::*;
+pub use duplicates::*;
 pub use error::*;
 pub use file::*;
+pub use game::*;
+pub use lint::*;
+pub use locate::*;
 pub use lump::*;
 pub use name::*;
+pub use namespace::*;
+pub use record::*;
+pub use resolved::*;
 pub use wad::*;
 
 #[cfg(test)]
 pub(crate) mod test;
 
+mod builder;
+mod classify;
+mod compact;
 mod cursor;
+mod diagnostics;
+mod dir;
+mod duplicates;
 mod error;
 mod file;
+mod folders;
+mod game;
+mod lint;
+mod locate;
 mod lump;
 mod name;
+mod namespace;
+mod pk3;
+mod record;
+mod resolved;
 #[allow(clippy::module_inception)]
 mod wad;
@@ -1,10 +1,255 @@
-/// Reads a name from a raw 8-byte, NUL padded byte array.
-///
-/// This function does not check if the name contains only legal ASCII characters. Non-ASCII bytes
-/// are treated as Latin-1, where all bytes are valid and map 1-to-1 to the corresponding Unicode
-/// codepoints.
-pub fn parse_name(raw: &[u8; 8]) -> String {
-    let nul_index = raw.iter().position(|&ch| ch == b'\0').unwrap_or(raw.len());
-    let raw = &raw[..nul_index];
-    raw.iter().copied().map(|b| b as char).collect()
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// A lump name: up to 8 bytes, NUL padded. Names are case insensitive, like in DOOM, and always
+/// stored in uppercase.
+///
+/// `LumpName` is `Copy` and compares, hashes, and orders like the uppercased string, so it's a
+/// cheap key for maps and sets. It can be compared directly with strings:
+///
+/// ```
+/// use dusty_room::wad::LumpName;
+///
+/// let name: LumpName = "w94_1".parse()?;
+/// assert_eq!(name, "W94_1");
+/// assert_eq!(name, "w94_1");
+/// assert_eq!(name.to_string(), "W94_1");
+/// #
+/// # Ok::<(), dusty_room::wad::LumpNameError>(())
+/// ```
+///
+/// Names read from files aren't validated, since some WADs in the wild have names with illegal
+/// characters. Non-ASCII bytes are treated as Latin-1, where all bytes are valid and map 1-to-1 to
+/// the corresponding Unicode codepoints.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LumpName([u8; 8]);
+
+/// The error returned when a string isn't a [legal] lump name.
+///
+/// [legal]: LumpName::new
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("bad lump name {0:?}")]
+pub struct LumpNameError(String);
+
+impl LumpName {
+    /// Creates a lump name, converting it to uppercase. Returns an error unless the name is 1-8
+    /// characters, each of which is a letter, a digit, or one of `[`, `]`, `-`, `_`, or `\`.
+    pub fn new(name: &str) -> Result<Self, LumpNameError> {
+        let upper = name.to_ascii_uppercase();
+        if !is_legal_name(&upper) {
+            return Err(LumpNameError(name.to_owned()));
+        }
+
+        let mut raw = [0; 8];
+        raw[..upper.len()].copy_from_slice(upper.as_bytes());
+        Ok(Self(raw))
+    }
+
+    /// Reads a name from a raw 8-byte, NUL padded byte array, converting it to uppercase. Anything
+    /// after the first NUL is ignored.
+    ///
+    /// This function does not check if the name contains only legal characters.
+    pub fn from_raw(raw: &[u8; 8]) -> Self {
+        let mut name = [0; 8];
+        for (to, &from) in name.iter_mut().zip(raw.iter().take_while(|&&b| b != b'\0')) {
+            *to = from.to_ascii_uppercase();
+        }
+        Self(name)
+    }
+
+    /// Like [`new`] but without checking for legal characters. Returns `None` if the name is longer
+    /// than 8 characters or has characters that can't be represented as Latin-1.
+    ///
+    /// [`new`]: Self::new
+    pub(crate) fn new_unvalidated(name: &str) -> Option<Self> {
+        let mut raw = [0; 8];
+        let mut chars = name.chars();
+
+        for to in raw.iter_mut() {
+            match chars.next() {
+                Some(ch) => *to = u8::try_from(ch).ok()?.to_ascii_uppercase(),
+                None => break,
+            }
+        }
+
+        match chars.next() {
+            Some(_) => None,
+            None => Some(Self(raw)),
+        }
+    }
+
+    /// The name as a raw 8-byte, NUL padded byte array. This is the inverse of [`from_raw`].
+    ///
+    /// [`from_raw`]: Self::from_raw
+    pub fn to_raw(self) -> [u8; 8] {
+        self.0
+    }
+
+    /// The name's bytes, without NUL padding.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..self.len()]
+    }
+
+    /// The length of the name in bytes.
+    pub fn len(&self) -> usize {
+        self.0.iter().position(|&b| b == b'\0').unwrap_or(8)
+    }
+
+    /// Returns `true` if the name is empty. Empty names aren't legal but can be found in malformed
+    /// files.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks if the name is legal. See [`new`] for the rules.
+    ///
+    /// [`new`]: Self::new
+    pub fn is_legal(&self) -> bool {
+        !self.is_empty() && self.chars().all(is_legal_char)
+    }
+
+    fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.as_bytes().iter().map(|&b| b as char)
+    }
+}
+
+impl FromStr for LumpName {
+    type Err = LumpNameError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<&str> for LumpName {
+    type Error = LumpNameError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl AsRef<[u8]> for LumpName {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq<str> for LumpName {
+    /// Compares case insensitively.
+    fn eq(&self, other: &str) -> bool {
+        self.chars().eq(other.chars().map(|ch| ch.to_ascii_uppercase()))
+    }
+}
+
+impl PartialEq<&str> for LumpName {
+    /// Compares case insensitively.
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<LumpName> for str {
+    fn eq(&self, other: &LumpName) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<LumpName> for &str {
+    fn eq(&self, other: &LumpName) -> bool {
+        other == *self
+    }
+}
+
+impl fmt::Display for LumpName {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.chars().try_for_each(|ch| fmt::Write::write_char(fmt, ch))
+    }
+}
+
+impl fmt::Debug for LumpName {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:?}", self.to_string())
+    }
+}
+
+/// A name that lumps can be looked up by: a [`LumpName`] or a string. Strings are case insensitive.
+pub trait ToLumpName: fmt::Display {
+    /// Converts to a `LumpName`. Returns `None` if the name can't be represented as one, in which
+    /// case no lump could possibly have it.
+    fn to_lump_name(&self) -> Option<LumpName>;
+}
+
+impl ToLumpName for LumpName {
+    fn to_lump_name(&self) -> Option<LumpName> {
+        Some(*self)
+    }
+}
+
+impl ToLumpName for str {
+    fn to_lump_name(&self) -> Option<LumpName> {
+        LumpName::new_unvalidated(self)
+    }
+}
+
+impl ToLumpName for String {
+    fn to_lump_name(&self) -> Option<LumpName> {
+        LumpName::new_unvalidated(self)
+    }
+}
+
+impl<T: ToLumpName + ?Sized> ToLumpName for &T {
+    fn to_lump_name(&self) -> Option<LumpName> {
+        (**self).to_lump_name()
+    }
+}
+
+/// Reads a name from a raw 8-byte, NUL padded byte array. Equivalent to [`LumpName::from_raw`].
+pub fn parse_name(raw: &[u8; 8]) -> LumpName {
+    LumpName::from_raw(raw)
+}
+
+/// Checks if a lump name is legal: 1-8 characters, each of which is an uppercase letter, a digit,
+/// or one of `[`, `]`, `-`, `_`, or `\`.
+pub fn is_legal_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 8 && name.chars().all(is_legal_char)
+}
+
+fn is_legal_char(ch: char) -> bool {
+    matches!(ch, 'A'..='Z' | '0'..='9' | '[' | ']' | '-' | '_' | '\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lump_name() {
+        let name = LumpName::new("w94_1").unwrap();
+        assert_eq!(name, "W94_1");
+        assert_eq!(name, "w94_1");
+        assert_ne!(name, "W94_10");
+        assert_eq!(name.len(), 5);
+        assert_eq!(name.to_raw(), *b"W94_1\0\0\0");
+        assert_eq!(format!("{:?}", name), "\"W94_1\"");
+
+        assert_eq!(LumpName::from_raw(b"sw18_7\0x"), name_of("SW18_7"));
+        assert_eq!(LumpName::from_raw(b"TOOLONG!"), "TOOLONG!");
+        assert!(!LumpName::from_raw(b"b@d\0\0\0\0\0").is_legal());
+        assert!(LumpName::from_raw(b"\xe9\0\0\0\0\0\0\0") == "\u{e9}");
+
+        assert_matches!(LumpName::new(""), Err(_));
+        assert_matches!(LumpName::new("NINECHARS"), Err(_));
+        assert_matches!("b@d".parse::<LumpName>(), Err(_));
+
+        assert!(name_of("A") < name_of("AB"));
+        assert!(name_of("AB") < name_of("B"));
+    }
+
+    fn name_of(name: &str) -> LumpName {
+        name.parse().unwrap()
+    }
 }
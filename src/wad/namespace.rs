@@ -0,0 +1,156 @@
+use std::fmt;
+
+/// A group of related lumps delimited by `*_START` and `*_END` marker lumps. Sprites, flats, and
+/// patches live in their own namespaces so that their names don't clash with each other or with
+/// other lumps.
+///
+/// Each namespace has a primary pair of markers used by the IWADs, such as `F_START` and `F_END`.
+/// PWADs often use doubled markers like `FF_START` and `FF_END` instead, a convention that dates
+/// back to DeuSF and is understood by most source ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Namespace {
+    /// Lumps that aren't inside any namespace markers: maps, sounds, music, `PLAYPAL`, etc.
+    Global,
+
+    /// Sprites, between `S_START` and `S_END`.
+    Sprites,
+
+    /// Flats, between `F_START` and `F_END`.
+    Flats,
+
+    /// Wall patches, between `P_START` and `P_END`.
+    Patches,
+
+    /// Boom's extra colormaps, between `C_START` and `C_END`.
+    Colormaps,
+
+    /// ZDoom's standalone textures, between `TX_START` and `TX_END`.
+    Textures,
+}
+
+impl Namespace {
+    /// All of the namespaces.
+    pub const ALL: [Self; 6] =
+        [Self::Global, Self::Sprites, Self::Flats, Self::Patches, Self::Colormaps, Self::Textures];
+
+    /// The start and end marker pairs that delimit this namespace, primary markers first.
+    /// [`Global`] has no markers.
+    ///
+    /// [`Global`]: Self::Global
+    pub fn markers(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Global => &[],
+            Self::Sprites => &[("S_START", "S_END"), ("SS_START", "SS_END")],
+            Self::Flats => &[("F_START", "F_END"), ("FF_START", "FF_END")],
+            Self::Patches => &[("P_START", "P_END"), ("PP_START", "PP_END")],
+            Self::Colormaps => &[("C_START", "C_END")],
+            Self::Textures => &[("TX_START", "TX_END")],
+        }
+    }
+
+    /// The primary start and end markers, for example `F_START` and `F_END`. Returns `None` for
+    /// [`Global`].
+    ///
+    /// [`Global`]: Self::Global
+    pub fn primary_markers(self) -> Option<(&'static str, &'static str)> {
+        self.markers().first().copied()
+    }
+
+    /// Identifies a namespace marker. Returns the namespace and whether it's a start marker, or
+    /// `None` if `name` isn't one of the markers listed by [`markers`].
+    ///
+    /// The numbered sub-markers inside the IWADs' blocks, like `F1_START` and `P2_END`, are not
+    /// namespace markers. Use [`is_sub_marker`] to detect those.
+    ///
+    /// [`markers`]: Self::markers
+    /// [`is_sub_marker`]: Self::is_sub_marker
+    pub fn from_marker(name: impl AsRef<[u8]>) -> Option<(Self, bool)> {
+        let name = name.as_ref();
+
+        Self::ALL.iter().find_map(|&namespace| {
+            namespace.markers().iter().find_map(|&(start, end)| {
+                if name == start.as_bytes() {
+                    Some((namespace, true))
+                } else if name == end.as_bytes() {
+                    Some((namespace, false))
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Checks if `name` is one of the numbered sub-markers the IWADs use inside their sprite, flat,
+    /// and patch blocks: `F1_START`, `F2_END`, `P3_START`, and so on. They carry no data.
+    pub fn is_sub_marker(name: impl AsRef<[u8]>) -> bool {
+        matches!(name.as_ref(), [b'S' | b'F' | b'P', b'1'..=b'9', b'_', suffix @ ..]
+            if suffix == b"START" || suffix == b"END")
+    }
+
+    /// The name of the folder that holds this namespace's lumps in a PK3 archive or a loose
+    /// directory, for example `flats`. Returns `None` for [`Global`], whose lumps are in the root
+    /// folder.
+    ///
+    /// [`Global`]: Self::Global
+    pub fn folder(self) -> Option<&'static str> {
+        match self {
+            Self::Global => None,
+            Self::Sprites => Some("sprites"),
+            Self::Flats => Some("flats"),
+            Self::Patches => Some("patches"),
+            Self::Colormaps => Some("colormaps"),
+            Self::Textures => Some("textures"),
+        }
+    }
+
+    /// Looks up the namespace for a PK3 or loose directory folder name. Case insensitive.
+    ///
+    /// Besides the folders listed by [`folder`], `sounds`, `music`, and `graphics` hold
+    /// [`Global`] lumps. Returns `None` for any other folder.
+    ///
+    /// [`folder`]: Self::folder
+    /// [`Global`]: Self::Global
+    pub fn from_folder(folder: &str) -> Option<Self> {
+        let folder = folder.to_ascii_lowercase();
+
+        if matches!(folder.as_str(), "sounds" | "music" | "graphics") {
+            return Some(Self::Global);
+        }
+
+        Self::ALL.iter().copied().find(|namespace| namespace.folder() == Some(folder.as_str()))
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Global => "global",
+            Self::Sprites => "sprites",
+            Self::Flats => "flats",
+            Self::Patches => "patches",
+            Self::Colormaps => "colormaps",
+            Self::Textures => "textures",
+        };
+
+        write!(fmt, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers() {
+        assert_eq!(Namespace::from_marker("F_START"), Some((Namespace::Flats, true)));
+        assert_eq!(Namespace::from_marker("FF_END"), Some((Namespace::Flats, false)));
+        assert_eq!(Namespace::from_marker("SS_START"), Some((Namespace::Sprites, true)));
+        assert_eq!(Namespace::from_marker("F1_START"), None);
+        assert_eq!(Namespace::from_marker("E1M1"), None);
+
+        assert!(Namespace::is_sub_marker("F1_START"));
+        assert!(Namespace::is_sub_marker("P3_END"));
+        assert!(!Namespace::is_sub_marker("F_START"));
+        assert!(!Namespace::is_sub_marker("FLOOR4_8"));
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::wad::folders::FolderLayout;
+use crate::wad::{self, Diagnostics, MalformedKind, WadFile};
+
+impl WadFile {
+    /// Loads a PK3 archive, the ZIP-based resource format used by modern source ports, and
+    /// presents it as a [PWAD] that can be layered into a [`Wad`] with [`Wad::add`].
+    ///
+    /// PK3s organize lumps into folders rather than marker lumps. Files in namespace folders such
+    /// as `flats/`, `sprites/`, and `patches/` are placed between the [markers] that
+    /// [`lumps_between`] expects, so a PK3's flats show up between `F_START` and `F_END`. Files in
+    /// the root folder become ordinary lumps, and WAD files in `maps/` are inlined as map blocks.
+    /// Files in other folders are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{Wad, WadFile};
+    ///
+    /// let wad = Wad::load("doom2.wad")?.add(WadFile::load_pk3("mod.pk3")?)?;
+    /// let map = wad.lumps_following("MAP01", 11)?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [PWAD]: crate::wad::WadKind::Pwad
+    /// [`Wad`]: crate::wad::Wad
+    /// [`Wad::add`]: crate::wad::Wad::add
+    /// [markers]: crate::wad::Namespace::markers
+    /// [`lumps_between`]: crate::wad::Wad::lumps_between
+    pub fn load_pk3(path: impl AsRef<Path>) -> wad::Result<Arc<Self>> {
+        Self::load_pk3_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Loads a PK3 archive. If `diagnostics` is [lenient], entries that can't be extracted and
+    /// map WADs that can't be loaded are reported and left out.
+    ///
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_pk3_with(
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| wad::Error::Io { path: path.to_owned(), source: err })?;
+        Self::load_pk3_reader_with(path, BufReader::new(file), diagnostics)
+    }
+
+    /// Loads a PK3 archive from a generic reader. See [`load_pk3`] for details.
+    ///
+    /// The `path` only used for display purposes, such as in error messages. It doesn't need to
+    /// point to an actual file on disk.
+    ///
+    /// [`load_pk3`]: Self::load_pk3
+    pub fn load_pk3_reader(
+        path: impl AsRef<Path>,
+        reader: impl Read + Seek,
+    ) -> wad::Result<Arc<Self>> {
+        Self::load_pk3_reader_with(path, reader, &mut Diagnostics::strict())
+    }
+
+    /// Loads a PK3 archive from a generic reader. See [`load_pk3_with`] for details.
+    ///
+    /// [`load_pk3_with`]: Self::load_pk3_with
+    pub fn load_pk3_reader_with(
+        path: impl AsRef<Path>,
+        reader: impl Read + Seek,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Arc<Self>> {
+        let path = path.as_ref();
+        let zip_error = |err| match err {
+            ZipError::Io(err) => wad::Error::Io { path: path.to_owned(), source: err },
+            err => wad::Error::malformed(path, MalformedKind::BadHeader, err.to_string()),
+        };
+
+        let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+        let mut layout = FolderLayout::new(path);
+
+        for index in 0..archive.len() {
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    diagnostics.report(zip_error(err))?;
+                    continue;
+                }
+            };
+            if entry.is_dir() {
+                continue;
+            }
+
+            // The WAD is untrusted so clamp how much memory is pre-allocated, and don't read past
+            // the declared size in case the entry decompresses to more than it claims.
+            let name = entry.name().to_owned();
+            let size = entry.size();
+            let mut data = Vec::with_capacity(size.clamp(0, 1 << 20) as usize);
+            if let Err(err) = (&mut entry).take(size.saturating_add(1)).read_to_end(&mut data) {
+                diagnostics.report(zip_error(err.into()))?;
+                continue;
+            }
+            if data.len() as u64 > size {
+                diagnostics.report(wad::Error::malformed(
+                    path,
+                    MalformedKind::TrailingData,
+                    format!("{} is larger than its declared size of {} bytes", name, size),
+                ))?;
+                continue;
+            }
+
+            layout.add(&name, Bytes::from(data));
+        }
+
+        layout.build(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::*;
+    use crate::wad::{Wad, WadBuilder, WadKind};
+
+    fn pk3(files: &[(&str, Vec<u8>)]) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        let mut raw = zip.finish().unwrap();
+        raw.set_position(0);
+        raw
+    }
+
+    #[test]
+    fn load() -> wad::Result<()> {
+        let mut map = WadBuilder::new(WadKind::Pwad);
+        map.map("WHATEVER", [("THINGS", vec![1; 10]), ("LINEDEFS", vec![2; 14])]);
+
+        let file = WadFile::load_pk3_reader(
+            "mod.pk3",
+            pk3(&[
+                ("flats/floor9.lmp", vec![3; 4096]),
+                ("flats/more/FLOOR10.raw", vec![4; 4096]),
+                ("sprites/trooa1.lmp", vec![5; 20]),
+                ("maps/map07.wad", map.build().to_vec()),
+                ("playpal.lmp", vec![6; 768]),
+                ("sounds/dspistol.lmp", vec![7; 30]),
+                ("docs/readme.txt", vec![8; 10]),
+                ("much_too_long.txt", vec![9; 10]),
+            ]),
+        )?;
+
+        assert_eq!(file.kind(), WadKind::Pwad);
+        assert_eq!(
+            file.lumps().map(|lump| lump.name().to_string()).collect::<Vec<_>>(),
+            [
+                "PLAYPAL", "DSPISTOL", "S_START", "TROOA1", "S_END", "F_START", "FLOOR9",
+                "FLOOR10", "F_END", "MAP07", "THINGS", "LINEDEFS",
+            ],
+        );
+
+        let wad = Wad::new(file)?;
+        assert_eq!(wad.lumps_between("F_START", "F_END")?.len(), 4);
+        assert_eq!(wad.lumps_following("MAP07", 3)?[1].data(), [1; 10]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient() -> wad::Result<()> {
+        let files = [("maps/map01.wad", b"not a WAD".to_vec()), ("playpal.lmp", vec![1; 768])];
+
+        assert_matches!(
+            WadFile::load_pk3_reader("bad.pk3", pk3(&files)),
+            Err(wad::Error::Malformed { .. })
+        );
+
+        let mut diagnostics = Diagnostics::lenient();
+        let file = WadFile::load_pk3_reader_with("bad.pk3", pk3(&files), &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 1);
+        assert_eq!(diagnostics.warnings()[0].kind(), Some(MalformedKind::BadHeader));
+        assert_eq!(file.lump("PLAYPAL")?.data(), [1; 768]);
+        assert!(file.try_lump("MAP01")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn larger_than_declared() -> wad::Result<()> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("playpal.lmp", options).unwrap();
+        zip.write_all(&[1; 768]).unwrap();
+        let mut raw = zip.finish().unwrap().into_inner();
+
+        // Shrink the uncompressed size in the local header and the central directory.
+        for (signature, offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let header = raw.windows(4).position(|window| window == signature).unwrap();
+            raw[header + offset..header + offset + 4].copy_from_slice(&16u32.to_le_bytes());
+        }
+
+        assert_matches!(
+            WadFile::load_pk3_reader("bomb.pk3", Cursor::new(raw.clone())),
+            Err(wad::Error::Malformed { .. })
+        );
+
+        let mut diagnostics = Diagnostics::lenient();
+        let file = WadFile::load_pk3_reader_with("bomb.pk3", Cursor::new(raw), &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings()[0].kind(), Some(MalformedKind::TrailingData));
+        assert!(file.try_lump("PLAYPAL")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_a_zip() {
+        assert_matches!(
+            WadFile::load_pk3_reader("bad.pk3", Cursor::new(b"PWAD".to_vec())),
+            Err(wad::Error::Malformed { .. })
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::convert::TryInto;
+
+use bytes::{Buf, BufMut};
+
+use crate::wad::LumpName;
+
+/// A fixed-size, little-endian record stored in a lump, such as a map vertex or a texture's patch
+/// placement. Records are read with [`Cursor::read`], which checks that there's enough data first.
+///
+/// Structs made of records can `#[derive(Record)]`. Their fields are laid out in declaration order
+/// with no padding.
+///
+/// # Examples
+///
+/// ```
+/// use dusty_room::wad::{LumpName, Record};
+///
+/// #[derive(Record)]
+/// struct Placement {
+///     x: i16,
+///     y: i16,
+///     patch: u16,
+///     _unused: [u16; 2],
+/// }
+///
+/// assert_eq!(Placement::SIZE, 10);
+///
+/// let placement = Placement { x: -1, y: 2, patch: 3, _unused: [0, 0] };
+/// let bytes = placement.to_bytes();
+/// assert_eq!(bytes, [0xFF, 0xFF, 2, 0, 3, 0, 0, 0, 0, 0]);
+///
+/// let decoded = Placement::decode(&mut &bytes[..]);
+/// assert_eq!((decoded.x, decoded.y, decoded.patch), (-1, 2, 3));
+/// ```
+///
+/// [`Cursor::read`]: crate::wad::Cursor::read
+pub trait Record: Sized {
+    /// The encoded size in bytes.
+    const SIZE: usize;
+
+    /// Decodes a record, advancing `buf` past it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than [`SIZE`] bytes remaining.
+    ///
+    /// [`SIZE`]: Self::SIZE
+    fn decode(buf: &mut &[u8]) -> Self;
+
+    /// Appends the encoded record to `buf`. Exactly [`SIZE`] bytes are written.
+    ///
+    /// [`SIZE`]: Self::SIZE
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Encodes the record into a new buffer.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SIZE);
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+pub use dusty_room_derive::Record;
+
+macro_rules! impl_record {
+    ($($ty:ty: $get:ident, $put:ident;)*) => {
+        $(
+            impl Record for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn decode(buf: &mut &[u8]) -> Self {
+                    buf.$get()
+                }
+
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.$put(*self);
+                }
+            }
+        )*
+    };
+}
+
+impl_record! {
+    u8: get_u8, put_u8;
+    i8: get_i8, put_i8;
+    u16: get_u16_le, put_u16_le;
+    i16: get_i16_le, put_i16_le;
+    u32: get_u32_le, put_u32_le;
+    i32: get_i32_le, put_i32_le;
+}
+
+/// An 8-byte, NUL padded name.
+impl Record for LumpName {
+    const SIZE: usize = 8;
+
+    fn decode(buf: &mut &[u8]) -> Self {
+        let raw = buf[..8].try_into().unwrap();
+        buf.advance(8);
+        LumpName::from_raw(raw)
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.put_slice(&self.to_raw());
+    }
+}
+
+impl<T: Record, const N: usize> Record for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn decode(buf: &mut &[u8]) -> Self {
+        std::array::from_fn(|_| T::decode(buf))
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for item in self {
+            item.encode(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::{self, WadBuilder, WadKind};
+
+    #[derive(Debug, PartialEq, Record)]
+    struct Named {
+        a: u8,
+        b: i16,
+        name: LumpName,
+        c: [u32; 2],
+    }
+
+    #[derive(Debug, PartialEq, Record)]
+    struct Tuple(i32, Named);
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(Named::SIZE, 19);
+        assert_eq!(Tuple::SIZE, 23);
+
+        let record = Tuple(-2, Named { a: 1, b: -1, name: "E1M1".parse().unwrap(), c: [3, 4] });
+        let bytes = record.to_bytes();
+        assert_eq!(bytes.len(), Tuple::SIZE);
+        assert_eq!(bytes[..7], [0xFE, 0xFF, 0xFF, 0xFF, 1, 0xFF, 0xFF]);
+        assert_eq!(&bytes[7..15], b"E1M1\0\0\0\0");
+
+        let mut buf = &bytes[..];
+        assert_eq!(Tuple::decode(&mut buf), record);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn cursor() -> wad::Result<()> {
+        let file = WadBuilder::new(WadKind::Pwad)
+            .lump("DATA", vec![1, 0, 2, 0, 3])
+            .build_file("test.wad")?;
+        let lump = file.lump("DATA")?;
+        let mut cursor = lump.cursor();
+
+        assert_eq!(cursor.read::<[u16; 2]>()?, [1, 2]);
+        assert_matches!(cursor.read::<u16>(), Err(wad::Error::Malformed { .. }));
+        assert_eq!(cursor.read::<u8>()?, 3);
+        cursor.done()
+    }
+}
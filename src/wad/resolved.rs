@@ -0,0 +1,415 @@
+use std::collections::{hash_map, HashMap, HashSet};
+use std::mem;
+use std::sync::Arc;
+
+use crate::wad::{self, Diagnostics, DuplicatePolicy, Lump, LumpName, Namespace, WadFile};
+
+/// The data lumps that can follow a map marker. Hexen adds `BEHAVIOR` to the ten used by DOOM.
+pub(super) const MAP_LUMPS: [&str; 11] = [
+    "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS", "REJECT",
+    "BLOCKMAP", "BEHAVIOR",
+];
+
+/// An entry in a [`Wad`]'s resolved directory: a lump that's in effect once all of the files are
+/// layered, the file that supplied it, and the copies it overrides.
+///
+/// See [`Wad::resolved_directory`].
+///
+/// [`Wad`]: crate::wad::Wad
+/// [`Wad::resolved_directory`]: crate::wad::Wad::resolved_directory
+#[derive(Clone, Debug)]
+pub struct ResolvedLump {
+    lump: Lump,
+    namespace: Namespace,
+    map: Option<LumpName>,
+    overrides: Vec<Lump>,
+    // Index of the lump that lookups see when deciding between duplicates: the lump itself, or
+    // for map data lumps the map marker.
+    rank: usize,
+}
+
+impl ResolvedLump {
+    /// The effective lump.
+    pub fn lump(&self) -> &Lump {
+        &self.lump
+    }
+
+    /// The file that supplied the lump.
+    pub fn file(&self) -> &Arc<WadFile> {
+        self.lump.file()
+    }
+
+    /// The lump name.
+    pub fn name(&self) -> LumpName {
+        self.lump.name()
+    }
+
+    /// The namespace the lump is in.
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    /// The map the lump belongs to if it's one of a map's data lumps, such as `THINGS` or
+    /// `SECTORS`. Map markers themselves don't belong to a map.
+    pub fn map(&self) -> Option<LumpName> {
+        self.map
+    }
+
+    /// The other copies of the lump that this one takes precedence over, in load order. Usually
+    /// they come from earlier files, but they can also be duplicates from the same file that lost
+    /// out under the [`DuplicatePolicy`].
+    pub fn overrides(&self) -> &[Lump] {
+        &self.overrides
+    }
+
+    /// Returns `true` if the lump overrides at least one other copy.
+    pub fn is_override(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+}
+
+/// A lump from a single file that may end up in the resolved directory.
+struct Candidate {
+    lump: Lump,
+    namespace: Namespace,
+    map: Option<LumpName>,
+    rank: usize,
+    // The name lookups use to find the lump, if any. Namespaced lumps are only found by merging.
+    lookup: Option<LumpName>,
+}
+
+impl Candidate {
+    fn key(&self) -> (Namespace, Option<LumpName>, LumpName) {
+        (self.namespace, self.map, self.lump.name())
+    }
+}
+
+/// Resolves the lumps in `files`, which are given in load order along with whether their
+/// namespaces are merged.
+///
+/// Lumps are identified by their namespace, name, and for map data lumps the map they belong to.
+/// A lump replaces any earlier lump with the same identity. Maps are replaced as a whole, same as
+/// with [`Wad::lumps_following`], so data lumps that a new copy of the map lacks are dropped.
+/// Likewise, files that aren't merged replace every namespace they have as a whole. Duplicates within the same file are settled by `policy`, the same as lookups and
+/// [`Wad::namespace`] settle them.
+///
+/// [`Wad::lumps_following`]: crate::wad::Wad::lumps_following
+/// [`Wad::namespace`]: crate::wad::Wad::namespace
+pub(super) fn resolve<'a>(
+    files: impl IntoIterator<Item = (&'a Arc<WadFile>, bool)>,
+    policy: DuplicatePolicy,
+    diagnostics: &mut Diagnostics,
+) -> wad::Result<Vec<ResolvedLump>> {
+    let mut entries: Vec<Option<ResolvedLump>> = Vec::new();
+    let mut slots: HashMap<(Namespace, Option<LumpName>, LumpName), usize> = HashMap::new();
+
+    for (file, merge) in files {
+        let candidates = candidates(file)?;
+        let mut chosen: HashMap<LumpName, Option<usize>> = HashMap::new();
+        let mut merged: HashMap<Namespace, HashSet<usize>> = HashMap::new();
+
+        // Evict the data lumps of maps this file replaces, and the lumps of namespaces it replaces
+        // if it isn't merged. The ones the file also has will take their old slots back below.
+        let maps: HashSet<LumpName> = candidates.iter().filter_map(|c| c.map).collect();
+        let mut namespaces: HashSet<Namespace> = HashSet::new();
+        if !merge {
+            namespaces.extend(file.namespaces()?.into_iter().filter(|&ns| ns != Namespace::Global));
+        }
+        let mut evicted = HashMap::new();
+        slots.retain(|&(namespace, map, name), &mut slot| {
+            let replaced =
+                namespaces.contains(&namespace) || map.is_some_and(|m| maps.contains(&m));
+            if replaced {
+                evicted.insert((namespace, map, name), slot);
+            }
+            !replaced
+        });
+
+        for candidate in candidates {
+            let key = candidate.key();
+
+            let slot = match slots.get(&key).copied().or_else(|| evicted.remove(&key)) {
+                Some(slot) => slot,
+                None => {
+                    slots.insert(key, entries.len());
+                    entries.push(Some(ResolvedLump {
+                        lump: candidate.lump,
+                        namespace: candidate.namespace,
+                        map: candidate.map,
+                        overrides: Vec::new(),
+                        rank: candidate.rank,
+                    }));
+                    continue;
+                }
+            };
+            slots.insert(key, slot);
+
+            let entry = entries[slot].as_mut().unwrap();
+            let replaces = if Arc::ptr_eq(entry.file(), file) {
+                // A duplicate from the same file. Pick the copy lookups or merging would.
+                let keeps = match candidate.lookup {
+                    Some(name) => {
+                        let winner = match chosen.get(&name) {
+                            Some(&index) => index,
+                            None => {
+                                let index = file.try_lump_index(name, policy, diagnostics)?;
+                                *chosen.entry(name).or_insert(index)
+                            }
+                        };
+                        winner == Some(entry.rank)
+                    }
+                    None => {
+                        let namespace = candidate.namespace;
+                        let winners = match merged.entry(namespace) {
+                            hash_map::Entry::Occupied(occupied) => occupied.into_mut(),
+                            hash_map::Entry::Vacant(vacant) => {
+                                let indices =
+                                    file.namespace_indices(namespace, policy, diagnostics)?;
+                                vacant.insert(indices.into_iter().collect())
+                            }
+                        };
+                        winners.contains(&entry.rank)
+                    }
+                };
+                !keeps
+            } else {
+                true
+            };
+
+            if replaces {
+                let old = mem::replace(&mut entry.lump, candidate.lump);
+                entry.overrides.push(old);
+                entry.rank = candidate.rank;
+            } else {
+                entry.overrides.push(candidate.lump);
+            }
+        }
+
+        for slot in evicted.into_values() {
+            entries[slot] = None;
+        }
+    }
+
+    Ok(entries.into_iter().flatten().collect())
+}
+
+/// Lists the lumps in a file that can be part of a resolved directory. Namespace markers are left
+/// out.
+fn candidates(file: &Arc<WadFile>) -> wad::Result<Vec<Candidate>> {
+    let namespaces = file.namespaces()?;
+    let lumps = file.try_lumps().collect::<wad::Result<Vec<_>>>()?;
+
+    let mut candidates = Vec::with_capacity(lumps.len());
+    // The open map block: its marker's name and index.
+    let mut map: Option<(LumpName, usize)> = None;
+
+    for (index, (lump, namespace)) in lumps.iter().zip(namespaces).enumerate() {
+        let name = lump.name();
+
+        if namespace != Namespace::Global {
+            map = None;
+            if Namespace::from_marker(name).is_none() && !Namespace::is_sub_marker(name) {
+                candidates.push(Candidate {
+                    lump: lump.clone(),
+                    namespace,
+                    map: None,
+                    rank: index,
+                    lookup: None,
+                });
+            }
+            continue;
+        }
+
+        match map {
+            Some((marker, marker_index)) if MAP_LUMPS.iter().any(|&l| name == l) => {
+                candidates.push(Candidate {
+                    lump: lump.clone(),
+                    namespace,
+                    map: Some(marker),
+                    rank: marker_index,
+                    lookup: Some(marker),
+                });
+            }
+
+            _ => {
+                // A map marker is an ordinary lump followed by `THINGS`.
+                map = match lumps.get(index + 1) {
+                    Some(next) if next.name() == "THINGS" => Some((name, index)),
+                    _ => None,
+                };
+                candidates.push(Candidate {
+                    lump: lump.clone(),
+                    namespace,
+                    map: None,
+                    rank: index,
+                    lookup: Some(name),
+                });
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wad::{
+        self, Diagnostics, DuplicatePolicy, MalformedKind, Namespace, Wad, WadBuilder, WadKind,
+    };
+
+    fn summary(wad: &Wad) -> wad::Result<Vec<String>> {
+        Ok(wad
+            .resolved_directory()?
+            .iter()
+            .map(|entry| {
+                let map = entry.map().map(|map| format!("{} ", map)).unwrap_or_default();
+                let overrides = entry
+                    .overrides()
+                    .iter()
+                    .map(|lump| format!(" > {}#{}", lump.file(), lump.index()))
+                    .collect::<String>();
+                format!(
+                    "{:?} {}{} from {}#{}{}",
+                    entry.namespace(),
+                    map,
+                    entry.name(),
+                    entry.file(),
+                    entry.lump().index(),
+                    overrides
+                )
+            })
+            .collect())
+    }
+
+    #[test]
+    fn resolved_directory() -> wad::Result<()> {
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.lump("PLAYPAL", vec![1; 768]);
+        iwad.marker("F_START").lump("FLAT1", vec![2; 4096]).lump("FLAT2", vec![3; 4096]);
+        iwad.marker("F_END");
+        iwad.map("MAP01", [("THINGS", vec![4; 10]), ("LINEDEFS", vec![5; 14])]);
+        iwad.map("MAP02", [("THINGS", vec![6; 10])]);
+
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.lump("PLAYPAL", vec![7; 768]);
+        pwad.marker("FF_START").lump("FLAT2", vec![8; 4096]).lump("FLAT3", vec![9; 4096]);
+        pwad.marker("FF_END");
+        pwad.map("MAP01", [("THINGS", vec![10; 10]), ("SECTORS", vec![11; 26])]);
+
+        let wad = Wad::new(iwad.build_file("iwad.wad")?)?.add(pwad.build_file("pwad.wad")?)?;
+
+        assert_eq!(
+            summary(&wad)?,
+            [
+                "Global PLAYPAL from pwad.wad#0 > iwad.wad#0",
+                "Flats FLAT1 from iwad.wad#2",
+                "Flats FLAT2 from pwad.wad#2 > iwad.wad#3",
+                "Global MAP01 from pwad.wad#5 > iwad.wad#5",
+                "Global MAP01 THINGS from pwad.wad#6 > iwad.wad#6",
+                "Global MAP02 from iwad.wad#8",
+                "Global MAP02 THINGS from iwad.wad#9",
+                "Flats FLAT3 from pwad.wad#3",
+                "Global MAP01 SECTORS from pwad.wad#7",
+            ]
+        );
+
+        let flats: Vec<_> = wad
+            .resolved_directory()?
+            .into_iter()
+            .filter(|entry| entry.namespace() == Namespace::Flats)
+            .map(|entry| entry.lump().clone())
+            .collect();
+        assert_eq!(
+            flats.iter().map(|lump| lump.data()).collect::<Vec<_>>(),
+            wad.namespace(Namespace::Flats)?.iter().map(|lump| lump.data()).collect::<Vec<_>>(),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unmerged() -> wad::Result<()> {
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.marker("F_START").lump("FLAT1", vec![1; 4096]).lump("FLAT2", vec![2; 4096]);
+        iwad.marker("F_END").marker("S_START").lump("TROOA1", vec![3; 8]).marker("S_END");
+
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.marker("F_START").lump("FLAT3", vec![4; 4096]).lump("FLAT2", vec![5; 4096]);
+        pwad.marker("F_END");
+
+        // The PWAD's flats replace the IWAD's. It has no sprites, so the IWAD's are kept.
+        let wad =
+            Wad::new(iwad.build_file("iwad.wad")?)?.add_unmerged(pwad.build_file("pwad.wad")?)?;
+        assert_eq!(
+            summary(&wad)?,
+            [
+                "Flats FLAT2 from pwad.wad#2 > iwad.wad#2",
+                "Sprites TROOA1 from iwad.wad#5",
+                "Flats FLAT3 from pwad.wad#1",
+            ]
+        );
+        assert_eq!(
+            wad.namespace(Namespace::Flats)?.iter().map(|lump| lump.name()).collect::<Vec<_>>(),
+            ["FLAT3", "FLAT2"]
+        );
+        assert_eq!(wad.namespace(Namespace::Sprites)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates() -> wad::Result<()> {
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.lump("DEMO1", vec![1; 10]).lump("DEMO1", vec![2; 10]);
+        let wad = Wad::new(pwad.build_file("dupes.wad")?)?;
+
+        assert_matches!(
+            wad.resolved_directory(),
+            Err(err) if err.kind() == Some(MalformedKind::Duplicate)
+        );
+        assert_eq!(
+            summary(&wad.with_duplicate_policy(DuplicatePolicy::First))?,
+            ["Global DEMO1 from dupes.wad#0 > dupes.wad#1"],
+        );
+        assert_eq!(
+            summary(&wad.with_duplicate_policy(DuplicatePolicy::Last))?,
+            ["Global DEMO1 from dupes.wad#1 > dupes.wad#0"],
+        );
+
+        let wad = wad.with_duplicate_policy(DuplicatePolicy::LastWithWarning);
+        assert_matches!(summary(&wad), Err(_));
+        let mut diagnostics = Diagnostics::lenient();
+        assert_eq!(wad.resolved_directory_with(&mut diagnostics)?.len(), 1);
+        assert_eq!(diagnostics.warnings().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn namespace_duplicates() -> wad::Result<()> {
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.marker("F_START").lump("FLAT1", vec![1; 4096]).lump("FLAT1", vec![2; 4096]);
+        pwad.lump("FLAT2", vec![3; 4096]).lump("FLAT2", vec![3; 4096]).marker("F_END");
+        let wad = Wad::new(pwad.build_file("dupes.wad")?)?;
+
+        // Namespaced duplicates follow the same rules as merging.
+        assert_matches!(
+            wad.resolved_directory(),
+            Err(err) if err.kind() == Some(MalformedKind::Duplicate)
+        );
+        assert_matches!(wad.namespace(Namespace::Flats), Err(_));
+
+        for (policy, data) in
+            [(DuplicatePolicy::First, [1; 4096]), (DuplicatePolicy::Last, [2; 4096])]
+        {
+            let wad = wad.with_duplicate_policy(policy);
+            let resolved = wad.resolved_directory()?;
+            assert_eq!(resolved.len(), 2);
+            assert_eq!(resolved[0].lump().data(), data);
+            assert_eq!(resolved[0].overrides().len(), 1);
+            assert_eq!(wad.namespace(Namespace::Flats)?[0].data(), data);
+        }
+
+        Ok(())
+    }
+}
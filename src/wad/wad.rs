@@ -1,7 +1,12 @@
+use std::collections::HashMap;
 use std::iter::once;
-use std::{path::Path, sync::Arc};
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::wad::{self, Lump, Lumps, WadFile, WadKind};
+use crate::wad::{
+    self, resolved, Diagnostics, DuplicatePolicy, Game, GameMission, IwadLocator, Lump, LumpName,
+    Lumps, MalformedKind, Namespace, ResolvedLump, ToLumpName, WadFile, WadKind,
+};
 
 /// A stack of WAD files layered on top of each other, with later files overlaying earlier ones.
 /// A `Wad` usually consists of an [IWAD] overlaid with zero or more [PWADs], an ordering which is
@@ -16,7 +21,16 @@ use crate::wad::{self, Lump, Lumps, WadFile, WadKind};
 #[must_use]
 pub struct Wad {
     initial: Arc<WadFile>,
-    patches: Vec<Arc<WadFile>>,
+    patches: Vec<Patch>,
+    duplicates: DuplicatePolicy,
+}
+
+/// A file overlaid on top of the initial one.
+#[derive(Clone, Debug)]
+struct Patch {
+    file: Arc<WadFile>,
+    /// Whether the file's namespaces are merged with the earlier files' or replace them.
+    merge: bool,
 }
 
 impl Wad {
@@ -24,11 +38,44 @@ impl Wad {
     ///
     /// [IWAD]: WadKind::Iwad
     pub fn load(path: impl AsRef<Path>) -> wad::Result<Self> {
-        let file = WadFile::load(path.as_ref())?;
+        Self::load_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Loads an initial [IWAD], recovering from a malformed lump directory if `diagnostics` is
+    /// [lenient].
+    ///
+    /// [IWAD]: WadKind::Iwad
+    /// [lenient]: Diagnostics::lenient
+    pub fn load_with(path: impl AsRef<Path>, diagnostics: &mut Diagnostics) -> wad::Result<Self> {
+        let file = WadFile::load_with(path.as_ref(), diagnostics)?;
         file.expect_kind(WadKind::Iwad)?;
         Self::new(file)
     }
 
+    /// Finds and loads the [IWAD] for `mission`, searching the [standard locations]. This lets
+    /// you ask for DOOM II instead of naming a particular file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{GameMission, Wad};
+    ///
+    /// let wad = Wad::load_game(GameMission::Doom2)?.patch("biotech.wad")?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with an [I/O error] if no IWAD is found.
+    ///
+    /// [IWAD]: WadKind::Iwad
+    /// [standard locations]: IwadLocator::new
+    /// [I/O error]: wad::Error::Io
+    pub fn load_game(mission: GameMission) -> wad::Result<Self> {
+        IwadLocator::new().load(mission)
+    }
+
     /// Loads an initial WAD without checking if it's an [IWAD].
     ///
     /// [IWAD]: WadKind::Iwad
@@ -50,14 +97,55 @@ impl Wad {
     /// [`load`]: Self::load
     /// [`expect_kind`]: WadFile::expect_kind
     pub fn new(file: Arc<WadFile>) -> wad::Result<Self> {
-        Ok(Self { initial: file, patches: Vec::new() })
+        Ok(Self { initial: file, patches: Vec::new(), duplicates: DuplicatePolicy::default() })
+    }
+
+    /// Returns a copy of this `Wad` that handles duplicate lump names according to `policy`. The
+    /// default is [`DuplicatePolicy::Error`].
+    ///
+    /// To use a policy with a single [`WadFile`], wrap it in a `Wad` with [`new`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::{DuplicatePolicy, Wad};
+    ///
+    /// let wad = Wad::load("doom.wad")?.with_duplicate_policy(DuplicatePolicy::Last);
+    /// let things = wad.lump("THINGS")?;
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [`new`]: Self::new
+    pub fn with_duplicate_policy(&self, policy: DuplicatePolicy) -> Self {
+        let mut clone = self.clone();
+        clone.duplicates = policy;
+        clone
+    }
+
+    /// The policy for handling duplicate lump names.
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicates
     }
 
     /// Overlays a [PWAD].
     ///
     /// [PWAD]: WadKind::Pwad
     pub fn patch(&self, path: impl AsRef<Path>) -> wad::Result<Self> {
-        let file = WadFile::load(path.as_ref())?;
+        self.patch_with(path, &mut Diagnostics::strict())
+    }
+
+    /// Overlays a [PWAD], recovering from a malformed lump directory if `diagnostics` is
+    /// [lenient].
+    ///
+    /// [PWAD]: WadKind::Pwad
+    /// [lenient]: Diagnostics::lenient
+    pub fn patch_with(
+        &self,
+        path: impl AsRef<Path>,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Self> {
+        let file = WadFile::load_with(path.as_ref(), diagnostics)?;
         file.expect_kind(WadKind::Pwad)?;
         self.add(file)
     }
@@ -83,7 +171,30 @@ impl Wad {
     /// [`expect_kind`]: WadFile::expect_kind
     pub fn add(&self, file: Arc<WadFile>) -> wad::Result<Self> {
         let mut clone = self.clone();
-        clone.patches.push(file);
+        clone.patches.push(Patch { file, merge: true });
+        Ok(clone)
+    }
+
+    /// Overlays a [PWAD] without merging its namespaces, the way vanilla DOOM's `-file` option
+    /// does. If the PWAD has an `F_START`/`F_END` block, the flats in it replace all of the flats
+    /// from earlier files instead of being [merged] with them, and likewise for sprites and the
+    /// other namespaces. Global lumps and maps override as usual.
+    ///
+    /// [PWAD]: WadKind::Pwad
+    /// [merged]: Self::namespace
+    pub fn patch_unmerged(&self, path: impl AsRef<Path>) -> wad::Result<Self> {
+        let file = WadFile::load(path.as_ref())?;
+        file.expect_kind(WadKind::Pwad)?;
+        self.add_unmerged(file)
+    }
+
+    /// Overlays an already loaded WAD file without merging its namespaces. See
+    /// [`patch_unmerged`].
+    ///
+    /// [`patch_unmerged`]: Self::patch_unmerged
+    pub fn add_unmerged(&self, file: Arc<WadFile>) -> wad::Result<Self> {
+        let mut clone = self.clone();
+        clone.patches.push(Patch { file, merge: false });
         Ok(clone)
     }
 
@@ -96,26 +207,67 @@ impl Wad {
     /// just to get a peek under the hood.
     ///
     /// [reverse]: Iterator::rev
-    pub fn files(&self) -> impl Iterator<Item = &WadFile> + DoubleEndedIterator {
+    pub fn files(&self) -> impl DoubleEndedIterator<Item = &WadFile> {
         let initial = once(&*self.initial);
-        let patches = self.patches.iter().map(|p| &**p);
+        let patches = self.patches.iter().map(|p| &*p.file);
         initial.chain(patches)
     }
 
+    /// Every file along with whether its namespaces are merged, in the order they were added.
+    fn layers(&self) -> impl Iterator<Item = (&Arc<WadFile>, bool)> {
+        let patches = self.patches.iter().map(|p| (&p.file, p.merge));
+        once((&self.initial, true)).chain(patches)
+    }
+
+    /// Identifies the game the initial [IWAD] is for. PWADs don't affect the result. See
+    /// [`WadFile::game`].
+    ///
+    /// [IWAD]: WadKind::Iwad
+    pub fn game(&self) -> wad::Result<Option<Game>> {
+        self.initial.game()
+    }
+
     /// Retrieves a unique lump by name. Lumps in later files override lumps from earlier ones.
     ///
     /// # Errors
     ///
     /// It is an error if the lump is missing.
-    pub fn lump(&self, name: &str) -> wad::Result<Lump> {
-        self.lookup(|patch| patch.try_lump(name), |initial| initial.lump(name))
+    pub fn lump(&self, name: impl ToLumpName) -> wad::Result<Lump> {
+        self.lump_with(name, &mut Diagnostics::strict())
+    }
+
+    /// Like [`lump`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`lump`]: Self::lump
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn lump_with(
+        &self,
+        name: impl ToLumpName,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lump> {
+        self.try_lump_with(&name, diagnostics)?
+            .ok_or_else(|| self.initial.error(MalformedKind::Missing, format!("{} missing", name)))
     }
 
     /// Retrieves a unique lump by name. Lumps in later files override lumps from earlier ones.
     ///
     /// Returns `Ok(None)` if the lump is missing.
-    pub fn try_lump(&self, name: &str) -> wad::Result<Option<Lump>> {
-        self.try_lookup(|file| file.try_lump(name))
+    pub fn try_lump(&self, name: impl ToLumpName) -> wad::Result<Option<Lump>> {
+        self.try_lump_with(name, &mut Diagnostics::strict())
+    }
+
+    /// Like [`try_lump`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`try_lump`]: Self::try_lump
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn try_lump_with(
+        &self,
+        name: impl ToLumpName,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<Lump>> {
+        self.try_lookup(diagnostics, |file, policy, diagnostics| {
+            file.try_lump_with(&name, policy, diagnostics)
+        })
     }
 
     /// Retrieves a block of `size > 0` lumps following a unique named marker. The marker lump is
@@ -128,11 +280,22 @@ impl Wad {
     /// # Panics
     ///
     /// Panics if `size == 0`.
-    pub fn lumps_following(&self, start: &str, size: usize) -> wad::Result<Lumps> {
-        self.lookup(
-            |patch| patch.try_lumps_following(start, size),
-            |initial| initial.lumps_following(start, size),
-        )
+    pub fn lumps_following(&self, start: impl ToLumpName, size: usize) -> wad::Result<Lumps> {
+        self.lumps_following_with(start, size, &mut Diagnostics::strict())
+    }
+
+    /// Like [`lumps_following`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`lumps_following`]: Self::lumps_following
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn lumps_following_with(
+        &self,
+        start: impl ToLumpName,
+        size: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_following_with(&start, size, diagnostics)?
+            .ok_or_else(|| self.initial.error(MalformedKind::Missing, format!("{} missing", start)))
     }
 
     /// Retrieves a block of `size > 0` lumps following a unique named marker. The marker lump is
@@ -143,8 +306,27 @@ impl Wad {
     /// # Panics
     ///
     /// Panics if `size == 0`.
-    pub fn try_lumps_following(&self, start: &str, size: usize) -> wad::Result<Option<Lumps>> {
-        self.try_lookup(|file| file.try_lumps_following(start, size))
+    pub fn try_lumps_following(
+        &self,
+        start: impl ToLumpName,
+        size: usize,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lumps_following_with(start, size, &mut Diagnostics::strict())
+    }
+
+    /// Like [`try_lumps_following`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`try_lumps_following`]: Self::try_lumps_following
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn try_lumps_following_with(
+        &self,
+        start: impl ToLumpName,
+        size: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lookup(diagnostics, |file, policy, diagnostics| {
+            file.try_lumps_following_with(&start, size, policy, diagnostics)
+        })
     }
 
     /// Retrieves a block of lumps between start and end markers. The marker lumps are included in
@@ -153,53 +335,208 @@ impl Wad {
     /// # Errors
     ///
     /// It is an error if the block is missing.
-    pub fn lumps_between(&self, start: &str, end: &str) -> wad::Result<Lumps> {
-        self.lookup(
-            |patch| patch.try_lumps_between(start, end),
-            |initial| initial.lumps_between(start, end),
-        )
+    pub fn lumps_between(
+        &self,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+    ) -> wad::Result<Lumps> {
+        self.lumps_between_with(start, end, &mut Diagnostics::strict())
+    }
+
+    /// Like [`lumps_between`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`lumps_between`]: Self::lumps_between
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn lumps_between_with(
+        &self,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Lumps> {
+        self.try_lumps_between_with(&start, &end, diagnostics)?.ok_or_else(|| {
+            self.initial.error(MalformedKind::Missing, format!("{} and {} missing", start, end))
+        })
     }
 
     /// Retrieves a block of lumps between start and end markers. The marker lumps are included in
     /// the result. Blocks in later wads override entire blocks from earlier files.
     ///
     /// Returns `Ok(None)` if the block is missing.
-    pub fn try_lumps_between(&self, start: &str, end: &str) -> wad::Result<Option<Lumps>> {
-        self.try_lookup(|file| file.try_lumps_between(start, end))
+    pub fn try_lumps_between(
+        &self,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lumps_between_with(start, end, &mut Diagnostics::strict())
+    }
+
+    /// Like [`try_lumps_between`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`try_lumps_between`]: Self::try_lumps_between
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn try_lumps_between_with(
+        &self,
+        start: impl ToLumpName,
+        end: impl ToLumpName,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Option<Lumps>> {
+        self.try_lookup(diagnostics, |file, policy, diagnostics| {
+            file.try_lumps_between_with(&start, &end, policy, diagnostics)
+        })
+    }
+
+    /// Retrieves all of the lumps in a [`Namespace`], merged across every file. This emulates the
+    /// `-merge` option of Chocolate Doom and the DeuSF tool: a PWAD that adds a few flats doesn't
+    /// hide the IWAD's flats, as it would with [`lumps_between`].
+    ///
+    /// Files are merged in order. A lump with the same name as one from an earlier file replaces
+    /// it in place, while new lumps are appended. Files added with [`add_unmerged`] are the
+    /// exception: if they have the namespace at all, it replaces everything before it. Marker lumps
+    /// are not included. If a single file
+    /// has more than one lump with the same name, the [`DuplicatePolicy`] picks one, the same as
+    /// with lookups.
+    ///
+    /// Sprites are merged by lump name only. Chocolate Doom also lets a PWAD sprite replace IWAD
+    /// sprites that draw the same frame and rotation under a different name, so that `TROOA1`
+    /// replaces `TROOA2A8`. That isn't done here: both lumps are kept.
+    ///
+    /// # Errors
+    ///
+    /// It is an error if any file's namespace markers are unbalanced, or if there are duplicates
+    /// the [`DuplicatePolicy`] doesn't allow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `namespace` is [`Namespace::Global`]. Global lumps aren't merged, they override.
+    ///
+    /// [`lumps_between`]: Self::lumps_between
+    /// [`add_unmerged`]: Self::add_unmerged
+    pub fn namespace(&self, namespace: Namespace) -> wad::Result<Vec<Lump>> {
+        self.namespace_with(namespace, &mut Diagnostics::strict())
     }
 
-    fn lookup<T>(
+    /// Retrieves all of the lumps in a [`Namespace`], merged across every file. See [`namespace`]
+    /// for details.
+    ///
+    /// # Errors
+    ///
+    /// Duplicates that the [`DuplicatePolicy`] doesn't allow are reported to `diagnostics`.
+    /// That's an error if it's strict, like with [`namespace`], and a warning if it's [lenient],
+    /// in which case the last one is used.
+    ///
+    /// [`namespace`]: Self::namespace
+    /// [lenient]: Diagnostics::lenient
+    pub fn namespace_with(
         &self,
-        try_lookup: impl Fn(&Arc<WadFile>) -> wad::Result<Option<T>>,
-        lookup: impl FnOnce(&Arc<WadFile>) -> wad::Result<T>,
-    ) -> wad::Result<T> {
-        for patch in self.patches.iter().rev() {
-            if let Some(value) = try_lookup(patch)? {
-                return Ok(value);
+        namespace: Namespace,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Vec<Lump>> {
+        assert_ne!(namespace, Namespace::Global);
+
+        let mut lumps: Vec<Lump> = Vec::new();
+        let mut indices: HashMap<LumpName, usize> = HashMap::new();
+
+        for (file, merge) in self.layers() {
+            if !merge && file.namespaces()?.contains(&namespace) {
+                lumps.clear();
+                indices.clear();
+            }
+            for lump in file.namespace_with(namespace, self.duplicates, diagnostics)? {
+                match indices.get(&lump.name()) {
+                    Some(&index) => lumps[index] = lump,
+                    None => {
+                        indices.insert(lump.name(), lumps.len());
+                        lumps.push(lump);
+                    }
+                }
             }
         }
 
-        lookup(&self.initial)
+        Ok(lumps)
     }
 
+    /// Resolves the effective lump directory: every lump that's in effect once the files are
+    /// layered, which file supplied it, and which other copies it overrides. This answers
+    /// questions like "which resources does this PWAD replace?"
+    ///
+    /// Lumps follow the same rules as lookups. Global lumps override lumps with the same name,
+    /// namespaced lumps are [merged], and maps are replaced as a whole block. Duplicate names
+    /// within a file are settled by the [`DuplicatePolicy`]. Namespace markers are not included.
+    ///
+    /// Entries are listed in the order they're first found. An override takes the place of the
+    /// lump it overrides.
+    ///
+    /// Every lump is read, so this can be slow for files that were opened [lazily].
+    ///
+    /// # Errors
+    ///
+    /// It is an error if any file's namespace markers are unbalanced, or if there are duplicates
+    /// the [`DuplicatePolicy`] doesn't allow.
+    ///
+    /// # Examples
+    ///
+    /// List the lumps a PWAD replaces:
+    ///
+    /// ```no_run
+    /// use dusty_room::wad::Wad;
+    ///
+    /// let wad = Wad::load("doom2.wad")?.patch("biotech.wad")?;
+    ///
+    /// for entry in wad.resolved_directory()? {
+    ///     if entry.is_override() {
+    ///         println!("{} replaces {} copies", entry.lump(), entry.overrides().len());
+    ///     }
+    /// }
+    /// #
+    /// # Ok::<(), dusty_room::wad::Error>(())
+    /// ```
+    ///
+    /// [merged]: Self::namespace
+    /// [lazily]: WadFile::load_lazy
+    pub fn resolved_directory(&self) -> wad::Result<Vec<ResolvedLump>> {
+        self.resolved_directory_with(&mut Diagnostics::strict())
+    }
+
+    /// Like [`resolved_directory`], but records [duplicate warnings] in `diagnostics`.
+    ///
+    /// [`resolved_directory`]: Self::resolved_directory
+    /// [duplicate warnings]: DuplicatePolicy::LastWithWarning
+    pub fn resolved_directory_with(
+        &self,
+        diagnostics: &mut Diagnostics,
+    ) -> wad::Result<Vec<ResolvedLump>> {
+        resolved::resolve(self.layers(), self.duplicates, diagnostics)
+    }
+
+    /// Searches the files from last to first, stopping at the first one that has a match.
     fn try_lookup<T>(
         &self,
-        try_lookup: impl Fn(&Arc<WadFile>) -> wad::Result<Option<T>>,
+        diagnostics: &mut Diagnostics,
+        try_lookup: impl Fn(&Arc<WadFile>, DuplicatePolicy, &mut Diagnostics) -> wad::Result<Option<T>>,
     ) -> wad::Result<Option<T>> {
-        for patch in self.patches.iter().rev() {
-            if let Some(value) = try_lookup(patch)? {
-                return Ok(Some(value));
+        for file in self.patches.iter().map(|p| &p.file).rev().chain(once(&self.initial)) {
+            let result = try_lookup(file, self.duplicates, diagnostics);
+            if !matches!(result, Ok(None)) {
+                return result;
             }
         }
 
-        try_lookup(&self.initial)
+        Ok(None)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use bytes::Bytes;
+
     use super::*;
     use crate::wad::test::*;
+    use crate::wad::WadBuilder;
 
     #[test]
     fn not_a_wad() {
@@ -284,20 +621,20 @@ mod tests {
                 .lumps_following("MAP01", 11)
                 .unwrap()
                 .iter()
-                .map(|lump| (lump.name(), lump.size()))
+                .map(|lump| (lump.name().to_string(), lump.size()))
                 .collect::<Vec<_>>(),
             [
-                ("MAP01", 0),
-                ("THINGS", 690),
-                ("LINEDEFS", 5180),
-                ("SIDEDEFS", 15870),
-                ("VERTEXES", 1532),
-                ("SEGS", 7212),
-                ("SSECTORS", 776),
-                ("NODES", 5404),
-                ("SECTORS", 1534),
-                ("REJECT", 436),
-                ("BLOCKMAP", 6418),
+                ("MAP01".to_owned(), 0),
+                ("THINGS".to_owned(), 690),
+                ("LINEDEFS".to_owned(), 5180),
+                ("SIDEDEFS".to_owned(), 15870),
+                ("VERTEXES".to_owned(), 1532),
+                ("SEGS".to_owned(), 7212),
+                ("SSECTORS".to_owned(), 776),
+                ("NODES".to_owned(), 5404),
+                ("SECTORS".to_owned(), 1534),
+                ("REJECT".to_owned(), 436),
+                ("BLOCKMAP".to_owned(), 6418),
             ],
         );
         assert_eq!(DOOM2_WAD.lumps_between("S_START", "S_END").unwrap().len(), 1383);
@@ -308,27 +645,278 @@ mod tests {
             wad.lumps_following("MAP01", 11)
                 .unwrap()
                 .iter()
-                .map(|lump| (lump.name(), lump.size()))
+                .map(|lump| (lump.name().to_string(), lump.size()))
                 .collect::<Vec<_>>(),
             [
-                ("MAP01", 0),
-                ("THINGS", 1050),
-                ("LINEDEFS", 5040),
-                ("SIDEDEFS", 17400),
-                ("VERTEXES", 1372),
-                ("SEGS", 7536),
-                ("SSECTORS", 984),
-                ("NODES", 6860),
-                ("SECTORS", 2184),
-                ("REJECT", 882),
-                ("BLOCKMAP", 4362),
+                ("MAP01".to_owned(), 0),
+                ("THINGS".to_owned(), 1050),
+                ("LINEDEFS".to_owned(), 5040),
+                ("SIDEDEFS".to_owned(), 17400),
+                ("VERTEXES".to_owned(), 1372),
+                ("SEGS".to_owned(), 7536),
+                ("SSECTORS".to_owned(), 984),
+                ("NODES".to_owned(), 6860),
+                ("SECTORS".to_owned(), 2184),
+                ("REJECT".to_owned(), 882),
+                ("BLOCKMAP".to_owned(), 4362),
             ],
         );
         assert_eq!(wad.lumps_between("S_START", "S_END").unwrap().len(), 1383);
         assert_eq!(wad.lumps_between("SS_START", "SS_END").unwrap().len(), 265);
     }
 
+    #[test]
+    fn load_mmap() -> wad::Result<()> {
+        let mapped = Wad::new(WadFile::load_mmap(DOOM_WAD_PATH)?)?;
+
+        assert_eq!(mapped.lump("DEMO1")?.data(), DOOM_WAD.lump("DEMO1")?.data());
+        assert_eq!(mapped.lumps_between("S_START", "S_END")?.len(), 485);
+
+        Ok(())
+    }
+
+    /// Records the byte ranges read from the inner reader and counts seeks.
+    struct TrackingReader<R> {
+        inner: R,
+        position: u64,
+        reads: Arc<Mutex<Vec<Range<u64>>>>,
+        seeks: Arc<AtomicUsize>,
+    }
+
+    impl<R: Read> Read for TrackingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.inner.read(buf)?;
+            self.reads.lock().unwrap().push(self.position..self.position + count as u64);
+            self.position += count as u64;
+            Ok(count)
+        }
+    }
+
+    impl<R: Seek> Seek for TrackingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seeks.fetch_add(1, Ordering::Relaxed);
+            self.position = self.inner.seek(pos)?;
+            Ok(self.position)
+        }
+    }
+
+    #[test]
+    fn load_lazy() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Iwad);
+        builder.lump("DEMO1", vec![1; 100]).marker("S_START").lump("TROOA1", vec![2; 50]);
+        builder.marker("S_END");
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let seeks = Arc::new(AtomicUsize::new(0));
+        let raw = TrackingReader {
+            inner: io::Cursor::new(builder.build().to_vec()),
+            position: 0,
+            reads: Arc::clone(&reads),
+            seeks: Arc::clone(&seeks),
+        };
+
+        // Lump data sits between the 12-byte header and the directory. Only the header and
+        // directory are read up front.
+        let data = 12..162;
+        let wad = Wad::new(WadFile::load_reader_lazy("lazy.wad", raw)?)?;
+        assert!(reads
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|read| read.end <= data.start || read.start >= data.end));
+
+        let read_count = reads.lock().unwrap().len();
+        assert_eq!(wad.lump("DEMO1")?.data(), [1; 100]);
+        assert!(reads.lock().unwrap()[read_count..].iter().any(|read| read.start == data.start));
+
+        // The second lookup is served from the cache.
+        let (read_count, seek_count) = (reads.lock().unwrap().len(), seeks.load(Ordering::Relaxed));
+        assert_eq!(wad.lump("DEMO1")?.data(), [1; 100]);
+        assert_eq!(reads.lock().unwrap().len(), read_count);
+        assert_eq!(seeks.load(Ordering::Relaxed), seek_count);
+
+        assert_eq!(wad.lumps_between("S_START", "S_END")?[1].data(), [2; 50]);
+        assert_matches!(wad.try_lump("MISSING"), Ok(None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("GOOD", vec![1; 10]).lump("BAD", vec![2; 10]).lump("BIG", vec![3; 10]);
+        let mut raw = builder.build().to_vec();
+        let size = raw.len();
+
+        // Corrupt the second lump's name and the third lump's size.
+        let directory = u32::from_le_bytes(raw[8..12].try_into().unwrap()) as usize;
+        raw[directory + 24..directory + 27].copy_from_slice(b"b@d");
+        raw[directory + 36..directory + 40].copy_from_slice(&1000u32.to_le_bytes());
+        let raw = Bytes::from(raw);
+
+        assert_matches!(WadFile::load_raw("bad.wad", raw.clone()), Err(_));
+
+        let mut diagnostics = Diagnostics::lenient();
+        let wad = Wad::new(WadFile::load_raw_with("bad.wad", raw.clone(), &mut diagnostics)?)?;
+        assert_matches!(
+            diagnostics.warnings(),
+            [bad_name, bad_size] if bad_name.kind() == Some(MalformedKind::BadName)
+                && bad_name.location().unwrap().index == 1
+                && bad_size.kind() == Some(MalformedKind::OutOfBounds)
+                && bad_size.location().unwrap().lump == "BIG"
+        );
+        assert_eq!(diagnostics.warnings()[1].to_string(), "bad.wad: BIG: bad size 1000");
+        assert_eq!(wad.lump("GOOD")?.data(), [1; 10]);
+        assert_eq!(wad.lump("BIG")?.size(), size - 32);
+
+        // Lazy loading recovers the same way.
+        let mut diagnostics = Diagnostics::lenient();
+        let reader = std::io::Cursor::new(raw.to_vec());
+        let file = WadFile::load_reader_lazy_with("bad.wad", reader, &mut diagnostics)?;
+        assert_eq!(diagnostics.warnings().len(), 2);
+        assert_eq!(file.lump("BIG")?.size(), size - 32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lump_at_end_of_file() -> wad::Result<()> {
+        // The directory comes first and the lump's data runs right up to the end of the file.
+        let mut raw = b"PWAD".to_vec();
+        raw.extend(1u32.to_le_bytes());
+        raw.extend(12u32.to_le_bytes());
+        raw.extend(28u32.to_le_bytes());
+        raw.extend(4u32.to_le_bytes());
+        raw.extend(b"LAST\0\0\0\0");
+        raw.extend([1, 2, 3, 4]);
+
+        let file = WadFile::load_raw("end.wad", Bytes::from(raw))?;
+        assert_eq!(file.lump("LAST")?.data(), [1, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn namespace() -> wad::Result<()> {
+        let mut iwad = WadBuilder::new(WadKind::Iwad);
+        iwad.marker("F_START").marker("F1_START").lump("FLAT1", vec![1; 4096]);
+        iwad.lump("FLAT2", vec![2; 4096]).marker("F1_END").marker("F_END");
+
+        let mut pwad = WadBuilder::new(WadKind::Pwad);
+        pwad.marker("FF_START").lump("FLAT2", vec![3; 4096]).lump("FLAT3", vec![4; 4096]);
+        pwad.marker("F_END").marker("S_START").marker("S_END");
+
+        let wad = Wad::new(iwad.build_file("iwad.wad")?)?.add(pwad.build_file("pwad.wad")?)?;
+        let flats = wad.namespace(Namespace::Flats)?;
+
+        assert_eq!(flats.iter().map(Lump::name).collect::<Vec<_>>(), ["FLAT1", "FLAT2", "FLAT3"]);
+        assert_eq!(flats[1].data(), [3; 4096]);
+        assert!(wad.namespace(Namespace::Sprites)?.is_empty());
+
+        // The unmerged block only has the PWAD's flats.
+        assert_eq!(wad.lumps_between("FF_START", "F_END")?.len(), 4);
+
+        let mut dupes = WadBuilder::new(WadKind::Pwad);
+        dupes.marker("F_START").lump("FLAT1", vec![5; 4096]).lump("FLAT1", vec![6; 4096]);
+        dupes.marker("F_END");
+        let dupes = wad.add(dupes.build_file("dupes.wad")?)?;
+        assert_matches!(
+            dupes.namespace(Namespace::Flats),
+            Err(err) if err.kind() == Some(MalformedKind::Duplicate)
+        );
+
+        let mut unbalanced = WadBuilder::new(WadKind::Pwad);
+        unbalanced.marker("FF_START").lump("FLAT4", vec![5; 4096]);
+        let wad = wad.add(unbalanced.build_file("unbalanced.wad")?)?;
+        assert_matches!(wad.namespace(Namespace::Flats), Err(_));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_sprites() {
+        let doom2_sprites = DOOM2_WAD.namespace(Namespace::Sprites).unwrap();
+        let biotech_sprites = BIOTECH_WAD.namespace(Namespace::Sprites).unwrap();
+
+        assert_eq!(doom2_sprites.len(), 1381);
+        assert!(biotech_sprites.len() >= doom2_sprites.len());
+        assert!(biotech_sprites
+            .iter()
+            .any(|lump| lump.file().path() == Path::new(BIOTECH_WAD_PATH)));
+    }
+
+    #[test]
+    fn duplicate_policy() -> wad::Result<()> {
+        let mut builder = WadBuilder::new(WadKind::Pwad);
+        builder.lump("DEHACKED", vec![1; 10]).lump("SAME", vec![2; 10]);
+        builder.map("MAP01", [("THINGS", vec![3; 10])]);
+        builder.map("MAP02", [("THINGS", vec![4; 10])]);
+        builder.lump("DEHACKED", vec![5; 10]).lump("SAME", vec![2; 10]);
+        let wad = Wad::new(builder.build_file("dupes.wad")?)?;
+
+        // Strict by default, but identical copies are okay.
+        assert_eq!(wad.duplicate_policy(), DuplicatePolicy::Error);
+        assert_matches!(wad.lump("DEHACKED"), Err(_));
+        assert_matches!(wad.lumps_following("THINGS", 1), Err(_));
+        assert_eq!(wad.lump("SAME")?.data(), [2; 10]);
+
+        let first = wad.with_duplicate_policy(DuplicatePolicy::First);
+        assert_eq!(first.lump("DEHACKED")?.data(), [1; 10]);
+        assert_eq!(first.lumps_following("THINGS", 1)?[0].data(), [3; 10]);
+
+        let last = wad.with_duplicate_policy(DuplicatePolicy::Last);
+        assert_eq!(last.lump("DEHACKED")?.data(), [5; 10]);
+        assert_eq!(last.lumps_between("MAP01", "THINGS")?.len(), 4);
+        let mut diagnostics = Diagnostics::lenient();
+        last.lump_with("DEHACKED", &mut diagnostics)?;
+        assert!(diagnostics.warnings().is_empty());
+
+        // Warnings are recorded when the diagnostics are lenient.
+        let warn = wad.with_duplicate_policy(DuplicatePolicy::LastWithWarning);
+        assert_eq!(warn.try_lump_with("DEHACKED", &mut diagnostics)?.unwrap().data(), [5; 10]);
+        assert_eq!(warn.lump_with("SAME", &mut diagnostics)?.data(), [2; 10]);
+        assert_matches!(warn.lump_with("MISSING", &mut diagnostics), Err(_));
+        assert_matches!(
+            diagnostics.warnings(),
+            [wad::Error::Malformed { kind: MalformedKind::Duplicate, location: Some(location), .. }]
+                if location.lump == "DEHACKED" && location.index == 6
+        );
+
+        // Without anywhere to record warnings, only actual duplicates are errors.
+        assert_eq!(warn.lump("MAP01")?.name(), "MAP01");
+        assert_eq!(warn.lump("SAME")?.data(), [2; 10]);
+        assert_eq!(warn.namespace(Namespace::Flats)?.len(), 0);
+        assert_eq!(warn.resolved_directory_with(&mut Diagnostics::lenient())?.len(), 6);
+        assert_matches!(
+            warn.lump("DEHACKED"),
+            Err(err) if err.kind() == Some(MalformedKind::Duplicate)
+        );
+        assert_matches!(warn.lump_with("DEHACKED", &mut Diagnostics::strict()), Err(_));
+        assert_matches!(warn.resolved_directory(), Err(_));
+
+        // A standalone file takes the policy with each lookup.
+        let file = builder.build_file("dupes.wad")?;
+        let mut diagnostics = Diagnostics::lenient();
+        assert_matches!(file.lump("DEHACKED"), Err(_));
+        assert_eq!(
+            file.lump_with("DEHACKED", DuplicatePolicy::First, &mut diagnostics)?.data(),
+            [1; 10]
+        );
+        assert_eq!(
+            file.lumps_following_with("THINGS", 1, DuplicatePolicy::Last, &mut diagnostics)?[0]
+                .data(),
+            [4; 10]
+        );
+        let policy = DuplicatePolicy::LastWithWarning;
+        assert_eq!(file.lumps_between_with("MAP02", "THINGS", policy, &mut diagnostics)?.len(), 2);
+        assert_eq!(diagnostics.warnings().len(), 1);
+
+        Ok(())
+    }
+
     // Make sure `Wad` is `Send` and `Sync`.
-    trait IsSendAndSync: Send + Sync {}
-    impl IsSendAndSync for Wad {}
+    #[test]
+    fn send_and_sync() {
+        fn is_send_and_sync<T: Send + Sync>() {}
+        is_send_and_sync::<Wad>();
+    }
 }